@@ -1,5 +1,3 @@
-BEGIN;
-
 CREATE TABLE IF NOT EXISTS "runs" (
     id                  INTEGER     NOT NULL,
     created_at          INTEGER     NOT NULL,
@@ -81,5 +79,3 @@ CREATE TABLE IF NOT EXISTS "vortices" (
 );
 
 CREATE INDEX IF NOT EXISTS "IX.Vortices_RunID_Dimension_Size" ON "vortices" (run_id, dimension, size);
-
-COMMIT;
\ No newline at end of file
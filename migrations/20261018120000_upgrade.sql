@@ -0,0 +1,300 @@
+-- Upgrades the initial schema to the lattices, boundary conditions, algorithms and analyses
+-- added since. Rows of the initial schema were simulated on periodic lattices whose height
+-- equals their size, except for the 1D lattices.
+
+-- Runs record the model, the geometry and the precision they were created with
+ALTER TABLE "runs" ADD COLUMN states INTEGER NULL;
+ALTER TABLE "runs" ADD COLUMN geometry TEXT NULL;
+ALTER TABLE "runs" ADD COLUMN precision TEXT NULL;
+
+-- Allocations are identified by their height and boundary conditions as well
+CREATE TABLE "allocations_new" (
+    id                  INTEGER     NOT NULL,
+
+    run_id              INTEGER     NOT NULL,
+    dimension           INTEGER     NOT NULL,
+    size                INTEGER     NOT NULL,
+    height              INTEGER     NOT NULL,
+    boundaries          TEXT        NOT NULL DEFAULT 'periodic periodic periodic',
+
+    node                TEXT            NULL,
+    process             INTEGER         NULL,
+    
+    allocated_at        INTEGER         NULL,
+    finished_at         INTEGER         NULL,
+
+    CONSTRAINT "PK.Allocations_ID" PRIMARY KEY (id),
+    CONSTRAINT "FK.Allocations_RunID" FOREIGN KEY (run_id) REFERENCES "runs" (id)
+);
+
+INSERT INTO "allocations_new" (id, run_id, dimension, size, height, node, process, allocated_at, finished_at)
+SELECT id, run_id, dimension, size, CASE WHEN dimension = 1 THEN 1 ELSE size END, node, process, allocated_at, finished_at
+FROM "allocations";
+
+DROP TABLE "allocations";
+ALTER TABLE "allocations_new" RENAME TO "allocations";
+
+CREATE UNIQUE INDEX "IX.Allocations_RunID_Dimension_Size_Height_Boundaries" ON "allocations" (run_id, dimension, size, height, boundaries);
+
+-- Results of the initial schema have no twists, helicity, vortex density or acceptance
+CREATE TABLE "results_new" (
+    id                  INTEGER     NOT NULL,
+
+    run_id              INTEGER     NOT NULL,
+    dimension           INTEGER     NOT NULL,
+    size                INTEGER     NOT NULL,
+    height              INTEGER     NOT NULL,
+    boundaries          TEXT        NOT NULL DEFAULT 'periodic periodic periodic',
+    temperature         REAL        NOT NULL,
+
+    twist_x             REAL            NULL,
+    twist_y             REAL            NULL,
+    twist_z             REAL            NULL,
+
+    energy              REAL        NOT NULL,
+    energy_std          REAL        NOT NULL,
+    energy_tau          REAL        NOT NULL,
+
+    energy_sqr          REAL        NOT NULL,
+    energy_sqr_std      REAL        NOT NULL,
+    energy_sqr_tau      REAL        NOT NULL,
+
+    magnet              REAL        NOT NULL,
+    magnet_std          REAL        NOT NULL,
+    magnet_tau          REAL        NOT NULL,
+
+    magnet_sqr          REAL        NOT NULL,
+    magnet_sqr_std      REAL        NOT NULL,
+    magnet_sqr_tau      REAL        NOT NULL,
+
+    specific_heat       REAL        NOT NULL,
+    specific_heat_std   REAL        NOT NULL,
+    magnet_suscept      REAL        NOT NULL,
+    magnet_suscept_std  REAL        NOT NULL,
+    helicity            REAL            NULL,
+    helicity_std        REAL            NULL,
+
+    vortex_density      REAL            NULL,
+    vortex_density_std  REAL            NULL,
+    vortex_density_tau  REAL            NULL,
+
+    acceptance          REAL            NULL,
+    violation           REAL            NULL,
+    violation_sqr       REAL            NULL,
+    violation_exp       REAL            NULL,
+    demon_temperature   REAL            NULL,
+    time_mc             INTEGER     NOT NULL,
+    time_boot           INTEGER     NOT NULL,
+
+    CONSTRAINT "PK.Results_ID" PRIMARY KEY (id),
+    CONSTRAINT "FK.Results_RunID" FOREIGN KEY (run_id) REFERENCES "runs" (id),
+    CONSTRAINT "FK.Results_RunID_Dimension_Size_Height_Boundaries" FOREIGN KEY (run_id, dimension, size, height, boundaries) REFERENCES "allocations" (run_id, dimension, size, height, boundaries)
+);
+
+INSERT INTO "results_new" (id, run_id, dimension, size, height, twist_x, twist_y, twist_z, temperature, energy, energy_std, energy_tau, energy_sqr, energy_sqr_std, energy_sqr_tau, magnet, magnet_std, magnet_tau, magnet_sqr, magnet_sqr_std, magnet_sqr_tau, specific_heat, specific_heat_std, magnet_suscept, magnet_suscept_std, time_mc, time_boot)
+SELECT id, run_id, dimension, size, CASE WHEN dimension = 1 THEN 1 ELSE size END, 0.0, CASE WHEN dimension >= 2 THEN 0.0 END, CASE WHEN dimension >= 3 THEN 0.0 END, temperature, energy, energy_std, energy_tau, energy_sqr, energy_sqr_std, energy_sqr_tau, magnet, magnet_std, magnet_tau, magnet_sqr, magnet_sqr_std, magnet_sqr_tau, specific_heat, specific_heat_std, magnet_suscept, magnet_suscept_std, time_mc, time_boot
+FROM "results";
+
+DROP TABLE "results";
+ALTER TABLE "results_new" RENAME TO "results";
+
+CREATE UNIQUE INDEX "IX.Results_RunID_Dimension_Size_Height_Boundaries_Temperature" ON "results" (run_id, dimension, size, height, boundaries, temperature);
+
+-- Vortices of the initial schema have no detected vortices and antivortices
+CREATE TABLE "vortices_new" (
+    id                  INTEGER     NOT NULL,
+
+    run_id              INTEGER     NOT NULL,
+    dimension           INTEGER     NOT NULL,
+    size                INTEGER     NOT NULL,
+    height              INTEGER     NOT NULL,
+    boundaries          TEXT        NOT NULL DEFAULT 'periodic periodic periodic',
+
+    temperature         REAL        NOT NULL,
+    spins               TEXT        NOT NULL,
+    vortices            TEXT            NULL,
+    antivortices        TEXT            NULL,
+
+    CONSTRAINT "PK.Vortices_ID" PRIMARY KEY (id),
+    CONSTRAINT "FK.Vortices_RunID" FOREIGN KEY (run_id) REFERENCES "runs" (id),
+    CONSTRAINT "FK.Vortices_RunID_Dimension_Size_Height_Boundaries" FOREIGN KEY (run_id, dimension, size, height, boundaries) REFERENCES "allocations" (run_id, dimension, size, height, boundaries)
+);
+
+INSERT INTO "vortices_new" (id, run_id, dimension, size, height, temperature, spins)
+SELECT id, run_id, dimension, size, CASE WHEN dimension = 1 THEN 1 ELSE size END, temperature, spins
+FROM "vortices";
+
+DROP TABLE "vortices";
+ALTER TABLE "vortices_new" RENAME TO "vortices";
+
+CREATE INDEX "IX.Vortices_RunID_Dimension_Size_Height_Boundaries" ON "vortices" (run_id, dimension, size, height, boundaries);
+
+-- Tables of the analyses added since
+CREATE TABLE "exchanges" (
+    id                  INTEGER     NOT NULL,
+
+    run_id              INTEGER     NOT NULL,
+    dimension           INTEGER     NOT NULL,
+    size                INTEGER     NOT NULL,
+    height              INTEGER     NOT NULL,
+    boundaries          TEXT        NOT NULL DEFAULT 'periodic periodic periodic',
+
+    temperature_low     REAL        NOT NULL,
+    temperature_high    REAL        NOT NULL,
+    acceptance          REAL        NOT NULL,
+
+    CONSTRAINT "PK.Exchanges_ID" PRIMARY KEY (id),
+    CONSTRAINT "FK.Exchanges_RunID" FOREIGN KEY (run_id) REFERENCES "runs" (id),
+    CONSTRAINT "FK.Exchanges_RunID_Dimension_Size_Height_Boundaries" FOREIGN KEY (run_id, dimension, size, height, boundaries) REFERENCES "allocations" (run_id, dimension, size, height, boundaries)
+);
+
+CREATE UNIQUE INDEX "IX.Exchanges_RunID_Dimension_Size_Height_Boundaries_Temperature" ON "exchanges" (run_id, dimension, size, height, boundaries, temperature_low, temperature_high);
+
+CREATE TABLE "densities" (
+    id                  INTEGER     NOT NULL,
+
+    run_id              INTEGER     NOT NULL,
+    dimension           INTEGER     NOT NULL,
+    size                INTEGER     NOT NULL,
+    height              INTEGER     NOT NULL,
+    boundaries          TEXT        NOT NULL DEFAULT 'periodic periodic periodic',
+
+    energy              REAL        NOT NULL,
+    ln_g                REAL        NOT NULL,
+
+    CONSTRAINT "PK.Densities_ID" PRIMARY KEY (id),
+    CONSTRAINT "FK.Densities_RunID" FOREIGN KEY (run_id) REFERENCES "runs" (id),
+    CONSTRAINT "FK.Densities_RunID_Dimension_Size_Height_Boundaries" FOREIGN KEY (run_id, dimension, size, height, boundaries) REFERENCES "allocations" (run_id, dimension, size, height, boundaries)
+);
+
+CREATE UNIQUE INDEX "IX.Densities_RunID_Dimension_Size_Height_Boundaries_Energy" ON "densities" (run_id, dimension, size, height, boundaries, energy);
+
+CREATE TABLE "reweighted" (
+    id                  INTEGER     NOT NULL,
+
+    run_id              INTEGER     NOT NULL,
+    dimension           INTEGER     NOT NULL,
+    size                INTEGER     NOT NULL,
+    height              INTEGER     NOT NULL,
+    boundaries          TEXT        NOT NULL DEFAULT 'periodic periodic periodic',
+    method              TEXT        NOT NULL,
+    temperature         REAL        NOT NULL,
+
+    specific_heat       REAL        NOT NULL,
+    specific_heat_std   REAL        NOT NULL,
+    magnet_suscept      REAL        NOT NULL,
+    magnet_suscept_std  REAL        NOT NULL,
+
+    CONSTRAINT "PK.Reweighted_ID" PRIMARY KEY (id),
+    CONSTRAINT "FK.Reweighted_RunID" FOREIGN KEY (run_id) REFERENCES "runs" (id),
+    CONSTRAINT "FK.Reweighted_RunID_Dimension_Size_Height_Boundaries" FOREIGN KEY (run_id, dimension, size, height, boundaries) REFERENCES "allocations" (run_id, dimension, size, height, boundaries)
+);
+
+CREATE UNIQUE INDEX "IX.Reweighted_RunID_Dimension_Size_Height_Boundaries_Method_Temperature" ON "reweighted" (run_id, dimension, size, height, boundaries, method, temperature);
+
+CREATE TABLE "peaks" (
+    id                  INTEGER     NOT NULL,
+
+    run_id              INTEGER     NOT NULL,
+    dimension           INTEGER     NOT NULL,
+    size                INTEGER     NOT NULL,
+    height              INTEGER     NOT NULL,
+    boundaries          TEXT        NOT NULL DEFAULT 'periodic periodic periodic',
+    method              TEXT        NOT NULL,
+    observable          TEXT        NOT NULL,
+
+    temperature         REAL        NOT NULL,
+    temperature_std     REAL        NOT NULL,
+    value               REAL        NOT NULL,
+    value_std           REAL        NOT NULL,
+
+    CONSTRAINT "PK.Peaks_ID" PRIMARY KEY (id),
+    CONSTRAINT "FK.Peaks_RunID" FOREIGN KEY (run_id) REFERENCES "runs" (id),
+    CONSTRAINT "FK.Peaks_RunID_Dimension_Size_Height_Boundaries" FOREIGN KEY (run_id, dimension, size, height, boundaries) REFERENCES "allocations" (run_id, dimension, size, height, boundaries)
+);
+
+CREATE UNIQUE INDEX "IX.Peaks_RunID_Dimension_Size_Height_Boundaries_Method_Observable" ON "peaks" (run_id, dimension, size, height, boundaries, method, observable);
+
+CREATE TABLE "quenches" (
+    id                  INTEGER     NOT NULL,
+
+    run_id              INTEGER     NOT NULL,
+    dimension           INTEGER     NOT NULL,
+    size                INTEGER     NOT NULL,
+    height              INTEGER     NOT NULL,
+    boundaries          TEXT        NOT NULL DEFAULT 'periodic periodic periodic',
+    rate                REAL        NOT NULL,
+    time                REAL        NOT NULL,
+
+    temperature         REAL        NOT NULL,
+    density             REAL        NOT NULL,
+    density_std         REAL        NOT NULL,
+
+    CONSTRAINT "PK.Quenches_ID" PRIMARY KEY (id),
+    CONSTRAINT "FK.Quenches_RunID" FOREIGN KEY (run_id) REFERENCES "runs" (id),
+    CONSTRAINT "FK.Quenches_RunID_Dimension_Size_Height_Boundaries" FOREIGN KEY (run_id, dimension, size, height, boundaries) REFERENCES "allocations" (run_id, dimension, size, height, boundaries)
+);
+
+CREATE UNIQUE INDEX "IX.Quenches_RunID_Dimension_Size_Height_Boundaries_Rate_Time" ON "quenches" (run_id, dimension, size, height, boundaries, rate, time);
+
+CREATE TABLE "annealings" (
+    id                  INTEGER     NOT NULL,
+
+    run_id              INTEGER     NOT NULL,
+    dimension           INTEGER     NOT NULL,
+    size                INTEGER     NOT NULL,
+    height              INTEGER     NOT NULL,
+    boundaries          TEXT        NOT NULL DEFAULT 'periodic periodic periodic',
+
+    energy              REAL        NOT NULL,
+    vortices            INTEGER     NOT NULL,
+    spins               TEXT        NOT NULL,
+    trajectory          TEXT        NOT NULL,
+
+    CONSTRAINT "PK.Annealings_ID" PRIMARY KEY (id),
+    CONSTRAINT "FK.Annealings_RunID" FOREIGN KEY (run_id) REFERENCES "runs" (id),
+    CONSTRAINT "FK.Annealings_RunID_Dimension_Size_Height_Boundaries" FOREIGN KEY (run_id, dimension, size, height, boundaries) REFERENCES "allocations" (run_id, dimension, size, height, boundaries)
+);
+
+CREATE INDEX "IX.Annealings_RunID_Dimension_Size_Height_Boundaries" ON "annealings" (run_id, dimension, size, height, boundaries);
+
+CREATE TABLE "dynamics" (
+    id                  INTEGER     NOT NULL,
+
+    run_id              INTEGER     NOT NULL,
+    dimension           INTEGER     NOT NULL,
+    size                INTEGER     NOT NULL,
+    height              INTEGER     NOT NULL,
+    boundaries          TEXT        NOT NULL DEFAULT 'periodic periodic periodic',
+    temperature         REAL        NOT NULL,
+    time                REAL        NOT NULL,
+
+    energy              REAL        NOT NULL,
+    magnet              REAL        NOT NULL,
+
+    CONSTRAINT "PK.Dynamics_ID" PRIMARY KEY (id),
+    CONSTRAINT "FK.Dynamics_RunID" FOREIGN KEY (run_id) REFERENCES "runs" (id),
+    CONSTRAINT "FK.Dynamics_RunID_Dimension_Size_Height_Boundaries" FOREIGN KEY (run_id, dimension, size, height, boundaries) REFERENCES "allocations" (run_id, dimension, size, height, boundaries)
+);
+
+CREATE UNIQUE INDEX "IX.Dynamics_RunID_Dimension_Size_Height_Boundaries_Temperature_Time" ON "dynamics" (run_id, dimension, size, height, boundaries, temperature, time);
+
+CREATE TABLE "snapshots" (
+    id                  INTEGER     NOT NULL,
+
+    run_id              INTEGER     NOT NULL,
+    dimension           INTEGER     NOT NULL,
+    size                INTEGER     NOT NULL,
+    height              INTEGER     NOT NULL,
+    boundaries          TEXT        NOT NULL DEFAULT 'periodic periodic periodic',
+    temperature         REAL        NOT NULL,
+    time                REAL        NOT NULL,
+
+    spins               TEXT        NOT NULL,
+
+    CONSTRAINT "PK.Snapshots_ID" PRIMARY KEY (id),
+    CONSTRAINT "FK.Snapshots_RunID" FOREIGN KEY (run_id) REFERENCES "runs" (id),
+    CONSTRAINT "FK.Snapshots_RunID_Dimension_Size_Height_Boundaries" FOREIGN KEY (run_id, dimension, size, height, boundaries) REFERENCES "allocations" (run_id, dimension, size, height, boundaries)
+);
+
+CREATE UNIQUE INDEX "IX.Snapshots_RunID_Dimension_Size_Height_Boundaries_Temperature_Time" ON "snapshots" (run_id, dimension, size, height, boundaries, temperature, time);
@@ -0,0 +1,3 @@
+-- Allocations record whether the canonical scans simulate them. The single sizes of the real time
+-- dynamics, the quenches and the annealing only reference their allocation from their own tables.
+ALTER TABLE "allocations" ADD COLUMN canonical INTEGER NOT NULL DEFAULT 1;
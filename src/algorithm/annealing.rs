@@ -0,0 +1,89 @@
+use crate::algorithm::quench::Schedule;
+use crate::algorithm::{Algorithm, Chain, Parameters};
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+
+/// The maximum number of zero temperature sweeps after the annealing.
+const RELAX_SWEEPS: usize = 10_000;
+
+/// The energy change of a zero temperature sweep below which the lattice is considered relaxed.
+const RELAX_TOLERANCE: f64 = 1e-12;
+
+/// Holds the result of a simulated annealing. The energy is given per spin, the trajectory holds
+/// the temperature and energy per spin after every sweep.
+pub struct Annealing {
+    pub energy: f64,
+    pub spins: Vec<f64>,
+    pub trajectory: Vec<(f64, f64)>,
+    pub vortices: usize,
+}
+
+/// Aligns every spin with its local field until the energy no longer decreases, which is the
+/// zero temperature limit of the heat bath algorithm.
+fn relax<L: Lattice>(lattice: &mut L) {
+    for _ in 0..RELAX_SWEEPS {
+        let mut sweep_energy = 0.0;
+        for i in 0..lattice.sites() {
+            let (field_cos, field_sin) = lattice.local_field(i);
+            let angle = f64::atan2(field_sin, field_cos).rem_euclid(MAX_ANGLE);
+            sweep_energy += lattice.energy_diff(i, angle);
+            lattice.update_angle(i, angle);
+        }
+
+        if -sweep_energy < RELAX_TOLERANCE {
+            break;
+        }
+    }
+}
+
+/// Slowly cools the lattice along the schedule within the given number of sweeps using any
+/// algorithm, where the temperature is updated via `Lattice::set_beta` before every sweep.
+/// Afterwards the lattice is relaxed at zero temperature. Returns the lowest energy
+/// configuration found, the energy trajectory and the number of vortices and antivortices
+/// remaining in the final configuration.
+pub fn anneal<L>(
+    rng: &mut fastrand::Rng,
+    parameters: Parameters,
+    lattice: &mut L,
+    schedule: Schedule,
+    knots: &[f64],
+    sweeps: usize,
+) -> Annealing
+where
+    L: Lattice,
+{
+    let mut trajectory = Vec::with_capacity(sweeps);
+    let mut best = (f64::INFINITY, Vec::new());
+    let mut chain = Chain::default();
+
+    for n in 1..=sweeps {
+        // Cool lattice and advance it by a single sweep
+        let temperature = schedule.temperature(knots, n as f64 / sweeps as f64);
+        lattice.set_beta(temperature.recip());
+        let series = lattice.simulate(parameters, &mut chain, rng, 1);
+
+        // Remember the configuration with the lowest energy
+        let energy = series.energies[0];
+        trajectory.push((temperature, energy));
+        if energy < best.0 {
+            best = (energy, (0..lattice.sites()).map(|i| lattice.angle(i)).collect());
+        }
+    }
+
+    // Quench remaining thermal fluctuations
+    relax(lattice);
+    let energy = lattice.normalize_per_spin(lattice.energy());
+    trajectory.push((0.0, energy));
+    if energy < best.0 {
+        best = (energy, (0..lattice.sites()).map(|i| lattice.angle(i)).collect());
+    }
+
+    Annealing {
+        energy: best.0,
+        spins: best.1,
+        trajectory,
+        vortices: lattice.vortex_density().map_or(0, |density| {
+            (density * lattice.sites() as f64).round() as usize
+        }),
+    }
+}
@@ -0,0 +1,122 @@
+use crate::algorithm::{measure, Algorithm, Series};
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+use rayon::prelude::*;
+
+/// The number of sites of one color which are updated by a single rayon task.
+const CHUNK: usize = 1024;
+
+pub trait Checkerboard: Algorithm {
+    /// Perform a single Metropolis sweep over all lattice sites, where all sites of one color
+    /// are updated at once. Since sites of the same color are never neighbours, their proposals
+    /// are evaluated in parallel chunks with independent random number generators. Returns the
+    /// number of accepted proposals and the energy and magnetization delta. The magnetization
+    /// delta is split into its (cos, sin) components.
+    fn checkerboard_sweep(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        colors: &[Vec<usize>],
+    ) -> (usize, f64, (f64, f64));
+
+    /// Run simulation using the checkerboard decomposed Metropolis algorithm for the given
+    /// number of sweeps. Returns the energy and magnetization observables and the acceptance
+    /// rate.
+    fn checkerboard(&mut self, rng: &mut fastrand::Rng, sweeps: usize, observables: bool)
+        -> Series;
+}
+
+/// Colors the lattice greedily such that no two neighbouring sites share a color. Bipartite
+/// lattices end up with the two red and black sublattices, odd periodic extents require
+/// additional colors. Returns the sites of every color.
+pub fn coloring<L: Lattice>(lattice: &L) -> Vec<Vec<usize>> {
+    let mut colors = vec![usize::MAX; lattice.sites()];
+    let mut sites: Vec<Vec<usize>> = Vec::new();
+    for i in 0..lattice.sites() {
+        // Pick the smallest color none of the colored neighbours has
+        let used = lattice
+            .neighbours(i)
+            .map(|(j, _)| colors[j])
+            .collect::<Vec<_>>();
+        let color = (0..).find(|c| !used.contains(c)).unwrap();
+        colors[i] = color;
+
+        if color == sites.len() {
+            sites.push(Vec::new());
+        }
+        sites[color].push(i);
+    }
+    sites
+}
+
+impl<T> Checkerboard for T
+where
+    T: Lattice,
+{
+    fn checkerboard_sweep(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        colors: &[Vec<usize>],
+    ) -> (usize, f64, (f64, f64)) {
+        // Prepare change variables
+        let mut accepted = 0;
+        let (mut chg_energy, mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0, 0.0);
+
+        for sites in colors {
+            // Evaluate proposals of all sites of this color in parallel
+            let seeds = (0..sites.len().div_ceil(CHUNK))
+                .map(|_| rng.u64(..))
+                .collect::<Vec<_>>();
+            let lattice = &*self;
+            let updates = sites
+                .par_chunks(CHUNK)
+                .zip(seeds)
+                .map(|(chunk, seed)| {
+                    let mut rng = fastrand::Rng::with_seed(seed);
+                    chunk
+                        .iter()
+                        .filter_map(|&i| {
+                            let angle = rng.f64() * MAX_ANGLE;
+                            let diff_energy = lattice.energy_diff(i, angle);
+                            (lattice.acceptance(diff_energy) > rng.f64()).then(|| {
+                                (i, angle, diff_energy, lattice.magnetization_diff(i, angle))
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            // Apply accepted proposals and update observables
+            for (i, angle, diff_energy, (diff_magnet_cos, diff_magnet_sin)) in
+                updates.into_iter().flatten()
+            {
+                accepted += 1;
+                chg_energy += diff_energy;
+                chg_magnet_cos += diff_magnet_cos;
+                chg_magnet_sin += diff_magnet_sin;
+                self.update_angle(i, angle);
+            }
+        }
+
+        // Return acceptance count and change in observables
+        (accepted, chg_energy, (chg_magnet_cos, chg_magnet_sin))
+    }
+
+    fn checkerboard(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        sweeps: usize,
+        observables: bool,
+    ) -> Series {
+        let colors = coloring(self);
+        let mut accepted = 0;
+        let mut series = measure(self, sweeps, observables, |lattice| {
+            let (count, chg_energy, chg_magnetization) = lattice.checkerboard_sweep(rng, &colors);
+            accepted += count;
+            (chg_energy, chg_magnetization)
+        });
+
+        // Acceptance rate over the measured sweeps
+        series.acceptance = accepted as f64 / (sweeps * self.sites()).max(1) as f64;
+        series
+    }
+}
@@ -0,0 +1,103 @@
+use crate::algorithm::metropolis::Metropolis;
+use crate::algorithm::{measure, Algorithm, Series};
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+
+pub trait Demon: Algorithm {
+    /// Perform a single microcanonical sweep over all lattice sites. Each site is assigned to
+    /// one of the demons, which absorbs the energy released by a proposal and pays for the
+    /// energy it costs. Proposals the demon cannot pay for are rejected, so that the total
+    /// energy of lattice and demons is conserved. Returns the number of accepted proposals and
+    /// the energy and magnetization delta. The magnetization delta is split into its (cos, sin)
+    /// components.
+    fn demon_sweep(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        demons: &mut [f64],
+    ) -> (usize, f64, (f64, f64));
+
+    /// Run simulation using the Creutz demon algorithm with the given demons for the given number
+    /// of sweeps. If there are no demons yet, the lattice is first brought to the energy of its
+    /// temperature by the given number of Metropolis sweeps and the given number of demons is
+    /// created, each starting with the energy T. The demons keep their energy for later calls.
+    /// Returns the energy and magnetization observables, the acceptance rate and the temperature
+    /// measured from the mean demon energy, since the demon energies are distributed as
+    /// exp(-E_d / T).
+    fn demon(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        sweeps: usize,
+        observables: bool,
+        demons: &mut Vec<f64>,
+        number: usize,
+        thermalization: usize,
+    ) -> Series;
+}
+
+impl<T> Demon for T
+where
+    T: Lattice,
+{
+    fn demon_sweep(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        demons: &mut [f64],
+    ) -> (usize, f64, (f64, f64)) {
+        // Prepare change variables
+        let mut accepted = 0;
+        let (mut chg_energy, mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0, 0.0);
+
+        // Go over all lattice sites
+        for i in 0..self.sites() {
+            // Generate random angle [0,2pi) and calculate difference in e
+            let angle = rng.f64() * MAX_ANGLE;
+            let diff_energy = self.energy_diff(i, angle);
+            let demon = &mut demons[i % demons.len()];
+
+            // The demon exchanges the energy difference if it can afford it, which requires no
+            // further random number
+            if *demon >= diff_energy {
+                let (diff_magnet_cos, diff_magnet_sin) = self.magnetization_diff(i, angle);
+                accepted += 1;
+                *demon -= diff_energy;
+                chg_energy += diff_energy;
+                chg_magnet_cos += diff_magnet_cos;
+                chg_magnet_sin += diff_magnet_sin;
+                self.update_angle(i, angle);
+            }
+        }
+
+        // Return acceptance count and change in observables
+        (accepted, chg_energy, (chg_magnet_cos, chg_magnet_sin))
+    }
+
+    fn demon(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        sweeps: usize,
+        observables: bool,
+        demons: &mut Vec<f64>,
+        number: usize,
+        thermalization: usize,
+    ) -> Series {
+        // Bring the lattice to the canonical energy of its temperature and let every demon start
+        // with the mean energy of its distribution
+        if demons.is_empty() {
+            self.metropolis_hastings(rng, thermalization, false, 0, None, &mut None);
+            *demons = vec![self.temperature(); number.max(1)];
+        }
+
+        let (mut accepted, mut demon_energy) = (0, 0.0);
+        let mut series = measure(self, sweeps, observables, |lattice| {
+            let (count, chg_energy, chg_magnetization) = lattice.demon_sweep(rng, demons);
+            accepted += count;
+            demon_energy += demons.iter().sum::<f64>() / demons.len() as f64;
+            (chg_energy, chg_magnetization)
+        });
+
+        // Acceptance rate and demon temperature over the measured sweeps
+        series.acceptance = accepted as f64 / (sweeps * self.sites()).max(1) as f64;
+        series.temperature = Some(demon_energy / sweeps.max(1) as f64);
+        series
+    }
+}
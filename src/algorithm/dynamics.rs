@@ -0,0 +1,163 @@
+use crate::algorithm::hybrid::gaussian;
+use crate::algorithm::{measure, Algorithm, Series};
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+
+pub trait Dynamics: Algorithm {
+    /// Performs a single Euler Maruyama step of the overdamped Langevin equation
+    /// damping * dtheta/dt = -dE/dtheta + noise with the given time step. All angles are updated
+    /// simultaneously from the forces of the current configuration. Returns the energy and
+    /// magnetization delta. The magnetization delta is split into its (cos, sin) components.
+    fn langevin_step(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        dt: f64,
+        damping: f64,
+    ) -> (f64, (f64, f64));
+
+    /// Performs random sequential Glauber updates for the given time step, where every spin
+    /// attempts on average 1 / damping updates per unit of time. A uniformly random angle is
+    /// accepted with the probability 1 / (1 + exp(beta * dE)). Returns the number of attempted
+    /// and accepted updates and the energy and magnetization delta. The magnetization delta is
+    /// split into its (cos, sin) components.
+    fn glauber_step(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        dt: f64,
+        damping: f64,
+    ) -> (usize, usize, f64, (f64, f64));
+
+    /// Run overdamped Langevin dynamics for the given number of time steps. Returns the energy
+    /// and magnetization observables after every time step.
+    fn langevin(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        steps: usize,
+        observables: bool,
+        dt: f64,
+        damping: f64,
+    ) -> Series;
+
+    /// Run Glauber dynamics for the given number of time steps. Returns the energy and
+    /// magnetization observables after every time step and the acceptance rate.
+    fn glauber(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        steps: usize,
+        observables: bool,
+        dt: f64,
+        damping: f64,
+    ) -> Series;
+}
+
+impl<T> Dynamics for T
+where
+    T: Lattice,
+{
+    fn langevin_step(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        dt: f64,
+        damping: f64,
+    ) -> (f64, (f64, f64)) {
+        let (energy, magnetization) = (self.energy(), self.magnetization());
+
+        // Calculate drift of all angles before updating any of them
+        let drifts = (0..self.sites())
+            .map(|i| {
+                let (field_cos, field_sin) = self.local_field(i);
+                let (sin, cos) = self.angle(i).sin_cos();
+                (cos * field_sin - sin * field_cos) * dt / damping
+            })
+            .collect::<Vec<_>>();
+
+        // Move angles by drift and thermal noise
+        let noise = f64::sqrt(2.0 * self.temperature() * dt / damping);
+        for (i, drift) in drifts.into_iter().enumerate() {
+            let angle = (self.angle(i) + drift + noise * gaussian(rng)).rem_euclid(MAX_ANGLE);
+            self.update_angle(i, angle);
+        }
+
+        // Return change in observables
+        let (magnet_cos, magnet_sin) = self.magnetization();
+        (
+            self.energy() - energy,
+            (magnet_cos - magnetization.0, magnet_sin - magnetization.1),
+        )
+    }
+
+    fn glauber_step(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        dt: f64,
+        damping: f64,
+    ) -> (usize, usize, f64, (f64, f64)) {
+        // Prepare change variables
+        let mut accepted = 0;
+        let (mut chg_energy, mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0, 0.0);
+
+        // Round the expected number of attempts stochastically, so that small steps still work
+        let expected = self.sites() as f64 * dt / damping;
+        let attempts = expected as usize + (expected.fract() > rng.f64()) as usize;
+
+        for _ in 0..attempts {
+            // Pick random site and generate random angle [0,2pi)
+            let i = rng.usize(..self.sites());
+            let angle = rng.f64() * MAX_ANGLE;
+            let diff_energy = self.energy_diff(i, angle);
+
+            // Check Glauber acceptance and update observables and spin
+            if (1.0 + f64::exp(self.beta() * diff_energy)).recip() > rng.f64() {
+                let (diff_magnet_cos, diff_magnet_sin) = self.magnetization_diff(i, angle);
+                accepted += 1;
+                chg_energy += diff_energy;
+                chg_magnet_cos += diff_magnet_cos;
+                chg_magnet_sin += diff_magnet_sin;
+                self.update_angle(i, angle);
+            }
+        }
+
+        // Return attempt and acceptance count and change in observables
+        (
+            attempts,
+            accepted,
+            chg_energy,
+            (chg_magnet_cos, chg_magnet_sin),
+        )
+    }
+
+    fn langevin(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        steps: usize,
+        observables: bool,
+        dt: f64,
+        damping: f64,
+    ) -> Series {
+        measure(self, steps, observables, |lattice| {
+            lattice.langevin_step(rng, dt, damping)
+        })
+    }
+
+    fn glauber(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        steps: usize,
+        observables: bool,
+        dt: f64,
+        damping: f64,
+    ) -> Series {
+        let (mut attempts, mut accepted) = (0, 0);
+        let mut series = measure(self, steps, observables, |lattice| {
+            let (count, acc, chg_energy, chg_magnetization) =
+                lattice.glauber_step(rng, dt, damping);
+            attempts += count;
+            accepted += acc;
+            (chg_energy, chg_magnetization)
+        });
+
+        // Acceptance rate over all attempted updates
+        series.acceptance = accepted as f64 / attempts.max(1) as f64;
+        series
+    }
+}
@@ -0,0 +1,79 @@
+use crate::algorithm::{measure, Algorithm, Series};
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+use std::f64::consts::PI;
+
+/// Below this concentration the von Mises distribution is indistinguishable from uniform.
+const MIN_KAPPA: f64 = 1e-8;
+
+pub trait HeatBath: Algorithm {
+    /// Perform a single sweep over all lattice sites by drawing each angle from the conditional
+    /// distribution exp(beta * h * cos(theta - phi)) of its local field. Returns the energy and
+    /// magnetization delta. The magnetization delta is split into its (cos, sin) components.
+    fn heat_bath_sweep(&mut self, rng: &mut fastrand::Rng) -> (f64, (f64, f64));
+
+    /// Run simulation using the heat bath algorithm for the given number of sweeps.
+    /// Returns the energy and magnetization observables.
+    fn heat_bath(&mut self, rng: &mut fastrand::Rng, sweeps: usize, observables: bool) -> Series;
+}
+
+/// Draws an angle in (-pi, pi] from the von Mises distribution with zero mean and concentration
+/// kappa using the rejection algorithm of Best and Fisher.
+pub fn von_mises(rng: &mut fastrand::Rng, kappa: f64) -> f64 {
+    if kappa < MIN_KAPPA {
+        return rng.f64() * MAX_ANGLE - PI;
+    }
+
+    // Parameters of the wrapped Cauchy envelope
+    let tau = 1.0 + f64::sqrt(1.0 + 4.0 * kappa * kappa);
+    let rho = (tau - f64::sqrt(2.0 * tau)) / (2.0 * kappa);
+    let r = (1.0 + rho * rho) / (2.0 * rho);
+
+    loop {
+        let z = f64::cos(PI * rng.f64());
+        let f = (1.0 + r * z) / (r + z);
+        let c = kappa * (r - f);
+
+        // Squeeze test first, then the exact logarithmic test
+        let u = 1.0 - rng.f64();
+        if c * (2.0 - c) > u || f64::ln(c / u) + 1.0 >= c {
+            let angle = f64::acos(f.clamp(-1.0, 1.0));
+            return if rng.bool() { angle } else { -angle };
+        }
+    }
+}
+
+impl<T> HeatBath for T
+where
+    T: Lattice,
+{
+    fn heat_bath_sweep(&mut self, rng: &mut fastrand::Rng) -> (f64, (f64, f64)) {
+        // Prepare change variables
+        let (mut chg_energy, mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0, 0.0);
+
+        // Go over all lattice sites
+        for i in 0..self.sites() {
+            // Draw new angle around the direction of the local field
+            let (field_cos, field_sin) = self.local_field(i);
+            let kappa = self.beta() * f64::hypot(field_cos, field_sin);
+            let angle = (f64::atan2(field_sin, field_cos) + von_mises(rng, kappa))
+                .rem_euclid(MAX_ANGLE);
+
+            // Every new angle is accepted, update observables and spin
+            let (diff_magnet_cos, diff_magnet_sin) = self.magnetization_diff(i, angle);
+            chg_energy += self.energy_diff(i, angle);
+            chg_magnet_cos += diff_magnet_cos;
+            chg_magnet_sin += diff_magnet_sin;
+            self.update_angle(i, angle);
+        }
+
+        // Return change in observables
+        (chg_energy, (chg_magnet_cos, chg_magnet_sin))
+    }
+
+    fn heat_bath(&mut self, rng: &mut fastrand::Rng, sweeps: usize, observables: bool) -> Series {
+        measure(self, sweeps, observables, |lattice| {
+            lattice.heat_bath_sweep(rng)
+        })
+    }
+}
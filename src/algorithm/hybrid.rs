@@ -0,0 +1,134 @@
+use crate::algorithm::{measure, Algorithm, Series};
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+
+/// Holds the mean of dH, dH^2 and exp(-dH) over all trajectories, where dH is the violation of
+/// the Hamiltonian H = beta * E + p^2 / 2. For a correct integrator the mean of exp(-dH) is one.
+#[derive(Clone, Copy, Default)]
+pub struct Violation {
+    pub mean: f64,
+    pub sqr_mean: f64,
+    pub exp_mean: f64,
+}
+
+pub trait HybridMonteCarlo: Algorithm {
+    /// Draws gaussian momenta conjugate to the angles and integrates a trajectory of the given
+    /// number of leapfrog steps with the given step size. The whole trajectory is accepted with
+    /// the probability min(1, exp(-dH)), otherwise the angles are restored. Returns whether the
+    /// trajectory was accepted, the violation dH and the energy and magnetization delta. The
+    /// magnetization delta is split into its (cos, sin) components.
+    fn trajectory(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        steps: usize,
+        step_size: f64,
+    ) -> (bool, f64, f64, (f64, f64));
+
+    /// Run simulation using hybrid Monte Carlo with one trajectory of the given length and step
+    /// size per sweep for the given number of sweeps. Returns the energy and magnetization
+    /// observables with the acceptance rate of the trajectories and the violation statistics.
+    fn hybrid_monte_carlo(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        sweeps: usize,
+        observables: bool,
+        length: f64,
+        step_size: f64,
+    ) -> Series;
+}
+
+/// Draws a standard normal distributed number using the Box Muller transform.
+pub fn gaussian(rng: &mut fastrand::Rng) -> f64 {
+    let radius = f64::sqrt(-2.0 * f64::ln(1.0 - rng.f64()));
+    radius * f64::cos(MAX_ANGLE * rng.f64())
+}
+
+/// Calculates the force -beta * dE/dtheta_i acting on the angle of the given site.
+fn force<L: Lattice>(lattice: &L, i: usize) -> f64 {
+    let (field_cos, field_sin) = lattice.local_field(i);
+    let (sin, cos) = lattice.angle(i).sin_cos();
+    lattice.beta() * (cos * field_sin - sin * field_cos)
+}
+
+impl<T> HybridMonteCarlo for T
+where
+    T: Lattice,
+{
+    fn trajectory(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        steps: usize,
+        step_size: f64,
+    ) -> (bool, f64, f64, (f64, f64)) {
+        // Remember the initial state for a rejection
+        let sites = self.sites();
+        let angles = (0..sites).map(|i| self.angle(i)).collect::<Vec<_>>();
+        let (energy, magnetization) = (self.energy(), self.magnetization());
+
+        // Draw momenta and calculate initial forces
+        let mut momenta = (0..sites).map(|_| gaussian(rng)).collect::<Vec<_>>();
+        let mut forces = (0..sites).map(|i| force(self, i)).collect::<Vec<_>>();
+        let kinetic = momenta.iter().map(|p| p * p).sum::<f64>() / 2.0;
+
+        // Integrate with leapfrog steps, the forces must be calculated from all updated angles
+        for _ in 0..steps {
+            for i in 0..sites {
+                momenta[i] += 0.5 * step_size * forces[i];
+                let angle = (self.angle(i) + step_size * momenta[i]).rem_euclid(MAX_ANGLE);
+                self.update_angle(i, angle);
+            }
+            for (i, force_i) in forces.iter_mut().enumerate() {
+                *force_i = force(self, i);
+                momenta[i] += 0.5 * step_size * *force_i;
+            }
+        }
+
+        // Calculate violation of the Hamiltonian
+        let chg_energy = self.energy() - energy;
+        let chg_kinetic = momenta.iter().map(|p| p * p).sum::<f64>() / 2.0 - kinetic;
+        let violation = self.beta() * chg_energy + chg_kinetic;
+
+        // Globally accept or restore initial angles
+        if f64::exp(f64::min(0.0, -violation)) > rng.f64() {
+            let (magnet_cos, magnet_sin) = self.magnetization();
+            let chg_magnetization = (magnet_cos - magnetization.0, magnet_sin - magnetization.1);
+            (true, violation, chg_energy, chg_magnetization)
+        } else {
+            for (i, angle) in angles.into_iter().enumerate() {
+                self.update_angle(i, angle);
+            }
+            (false, violation, 0.0, (0.0, 0.0))
+        }
+    }
+
+    fn hybrid_monte_carlo(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        sweeps: usize,
+        observables: bool,
+        length: f64,
+        step_size: f64,
+    ) -> Series {
+        let steps = usize::max((length / step_size).round() as usize, 1);
+        let (mut accepted, mut sum, mut sqr_sum, mut exp_sum) = (0, 0.0, 0.0, 0.0);
+        let mut series = measure(self, sweeps, observables, |lattice| {
+            let (acc, violation, chg_energy, chg_magnetization) =
+                lattice.trajectory(rng, steps, step_size);
+            accepted += acc as usize;
+            sum += violation;
+            sqr_sum += violation * violation;
+            exp_sum += f64::exp(-violation);
+            (chg_energy, chg_magnetization)
+        });
+
+        // Acceptance rate and violation statistics over all trajectories
+        let count = sweeps.max(1) as f64;
+        series.acceptance = accepted as f64 / count;
+        series.violation = Some(Violation {
+            mean: sum / count,
+            sqr_mean: sqr_sum / count,
+            exp_mean: exp_sum / count,
+        });
+        series
+    }
+}
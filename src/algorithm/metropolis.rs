@@ -1,49 +1,64 @@
-use crate::algorithm::Algorithm;
+use crate::algorithm::overrelaxation::Overrelaxation;
+use crate::algorithm::{measure, Algorithm, Series};
 use crate::lattice::Lattice;
+use std::f64::consts::PI;
+
+/// The proposal width for which the restricted proposal is equivalent to a uniform proposal.
+const MAX_WIDTH: f64 = PI;
+
+/// The smallest proposal width the adaptive tuning can shrink to.
+const MIN_WIDTH: f64 = 1e-3;
+
+/// The number of thermalization sweeps after which the proposal width is adjusted.
+const TUNING_INTERVAL: usize = 100;
 
 pub trait Metropolis: Algorithm {
-    /// Perform a single sweep over all lattice sites and returns the energy and magnetization
-    /// delta. The magnetization delta is split into its (cos, sin) components.
-    fn sweep(&mut self, rng: &mut fastrand::Rng) -> (f64, (f64, f64));
+    /// Perform a single sweep over all lattice sites proposing angles within the given width
+    /// around the current angle. Returns the number of accepted proposals and the energy and
+    /// magnetization delta. The magnetization delta is split into its (cos, sin) components.
+    fn sweep(&mut self, rng: &mut fastrand::Rng, width: f64) -> (usize, f64, (f64, f64));
+
+    /// Performs the given number of thermalization sweeps while tuning the proposal width
+    /// towards the target acceptance rate. Returns the tuned proposal width.
+    fn tune(&mut self, rng: &mut fastrand::Rng, sweeps: usize, target: f64) -> f64;
 
     /// Run simulation using the Metropolis Hastings algorithm for the given number of sweeps.
-    /// Returns two vectors with the energy and magnetization observables.
+    /// Each sweep is preceded by the given number of overrelaxation sweeps. If a target acceptance
+    /// rate is given, the proposal width is taken from the given width at unit temperature and
+    /// scaled to the current temperature. If it is not known yet, it is tuned during the given
+    /// number of thermalization sweeps and stored for later calls. Returns the energy and
+    /// magnetization observables together with the acceptance rate of the measured sweeps.
     fn metropolis_hastings(
         &mut self,
         rng: &mut fastrand::Rng,
         sweeps: usize,
-    ) -> (Vec<f64>, Vec<f64>);
-}
-
-impl<T> Algorithm for T
-where
-    T: Lattice + Metropolis,
-{
-    /// Runs the Metropolis Hastings algorithm on the lattice for the given number of sweeps.
-    /// Returns the energy and magnetization observables.
-    fn simulate(&mut self, rng: &mut fastrand::Rng, sweeps: usize) -> (Vec<f64>, Vec<f64>) {
-        self.metropolis_hastings(rng, sweeps)
-    }
+        observables: bool,
+        overrelaxation: usize,
+        target: Option<(f64, usize)>,
+        width: &mut Option<f64>,
+    ) -> Series;
 }
 
 impl<T> Metropolis for T
 where
     T: Lattice,
 {
-    fn sweep(&mut self, rng: &mut fastrand::Rng) -> (f64, (f64, f64)) {
+    fn sweep(&mut self, rng: &mut fastrand::Rng, width: f64) -> (usize, f64, (f64, f64)) {
         // Prepare change variables
+        let mut accepted = 0;
         let (mut chg_energy, mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0, 0.0);
 
         // Go over all lattice sites
         for i in 0..self.sites() {
-            // Generate random angle [0,2pi) and calculate difference in e and m
-            let angle = rng.f64() * crate::constants::MAX_ANGLE;
+            // Generate random angle within width and calculate difference in e and m
+            let angle = self.propose(rng, i, width);
             let diff_energy = self.energy_diff(i, angle);
             let (diff_magnet_cos, diff_magnet_sin) = self.magnetization_diff(i, angle);
 
             // Check acceptance ratio for energy difference and update observables and spin
             // if change is accepted
             if self.acceptance(diff_energy) > rng.f64() {
+                accepted += 1;
                 chg_energy += diff_energy;
                 chg_magnet_cos += diff_magnet_cos;
                 chg_magnet_sin += diff_magnet_sin;
@@ -51,36 +66,71 @@ where
             }
         }
 
-        // Return change in observables
-        (chg_energy, (chg_magnet_cos, chg_magnet_sin))
+        // Return acceptance count and change in observables
+        (accepted, chg_energy, (chg_magnet_cos, chg_magnet_sin))
+    }
+
+    fn tune(&mut self, rng: &mut fastrand::Rng, sweeps: usize, target: f64) -> f64 {
+        let mut width = MAX_WIDTH;
+        for start in (0..sweeps).step_by(TUNING_INTERVAL) {
+            // Measure acceptance rate over the interval
+            let interval = usize::min(TUNING_INTERVAL, sweeps - start);
+            let mut accepted = 0;
+            for _ in 0..interval {
+                accepted += self.sweep(rng, width).0;
+            }
+
+            // Widen the proposal if too many are accepted and narrow it otherwise
+            let rate = accepted as f64 / (interval * self.sites()) as f64;
+            width = (width * (rate / target).clamp(0.5, 2.0)).clamp(MIN_WIDTH, MAX_WIDTH);
+        }
+        width
     }
 
     fn metropolis_hastings(
         &mut self,
         rng: &mut fastrand::Rng,
         sweeps: usize,
-    ) -> (Vec<f64>, Vec<f64>) {
-        // Prepare results vectors
-        let mut energies = Vec::<f64>::with_capacity(sweeps);
-        let mut magnets = Vec::<f64>::with_capacity(sweeps);
-
-        // Calculate initial observables and sweeps over lattice
-        let (mut cur_energy, mut cur_magnetization) = (self.energy(), self.magnetization());
-        for _ in 0..sweeps {
-            // Perform sweep and collect running observables
-            let (chg_energy, chg_magnetization) = self.sweep(rng);
-            cur_energy += chg_energy;
-            cur_magnetization.0 += chg_magnetization.0;
-            cur_magnetization.1 += chg_magnetization.1;
-
-            // Push current observables to results
-            energies.push(self.normalize_per_spin(cur_energy));
-            magnets.push(self.normalize_per_spin(f64::sqrt(
-                cur_magnetization.0.powi(2) + cur_magnetization.1.powi(2),
-            )));
-        }
+        observables: bool,
+        overrelaxation: usize,
+        target: Option<(f64, usize)>,
+        width: &mut Option<f64>,
+    ) -> Series {
+        // Tune proposal width during the first thermalization or fall back to uniform proposals.
+        // The acceptance of small proposals only depends on beta times the squared width, so the
+        // width follows the temperature of annealing, quenches and exchanges by scaling with √T
+        let width = match target {
+            Some((rate, thermalization)) => {
+                let width = *width.get_or_insert_with(|| {
+                    self.tune(rng, thermalization, rate) * self.beta().sqrt()
+                });
+                (width * self.temperature().sqrt()).clamp(MIN_WIDTH, MAX_WIDTH)
+            }
+            None => MAX_WIDTH,
+        };
+
+        let mut accepted = 0;
+        let mut series = measure(self, sweeps, observables, |lattice| {
+            // Energy conserving overrelaxation sweeps only change the magnetization
+            let (mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0);
+            for _ in 0..overrelaxation {
+                let (diff_magnet_cos, diff_magnet_sin) = lattice.overrelax();
+                chg_magnet_cos += diff_magnet_cos;
+                chg_magnet_sin += diff_magnet_sin;
+            }
+
+            // Ergodic metropolis sweep
+            let (count, chg_energy, (diff_magnet_cos, diff_magnet_sin)) =
+                lattice.sweep(rng, width);
+            accepted += count;
+            (
+                chg_energy,
+                (chg_magnet_cos + diff_magnet_cos, chg_magnet_sin + diff_magnet_sin),
+            )
+        });
 
-        // Return results
-        (energies, magnets)
+        // Acceptance rate over the measured sweeps
+        series.acceptance = accepted as f64 / (sweeps * self.sites()).max(1) as f64;
+        series
     }
 }
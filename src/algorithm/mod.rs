@@ -1,8 +1,288 @@
+use crate::algorithm::checkerboard::Checkerboard;
+use crate::algorithm::demon::Demon;
+use crate::algorithm::dynamics::Dynamics;
+use crate::algorithm::heat_bath::HeatBath;
+use crate::algorithm::hybrid::{HybridMonteCarlo, Violation};
+use crate::algorithm::metropolis::Metropolis;
+use crate::algorithm::swendsen_wang::SwendsenWang;
+use crate::algorithm::vectorized::Vectorized;
+use crate::algorithm::wolff::Wolff;
+use crate::lattice::Lattice;
+
+pub mod annealing;
+pub mod checkerboard;
+pub mod demon;
+pub mod dynamics;
+pub mod heat_bath;
+pub mod hybrid;
 pub mod metropolis;
+pub mod multicanonical;
+pub mod overrelaxation;
+pub mod quench;
+pub mod swendsen_wang;
+pub mod tempering;
+pub mod vectorized;
+pub mod wang_landau;
+pub mod wolff;
+
+/// The Monte Carlo methods which can be selected at run time.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Method {
+    /// Single spin updates with a uniformly random proposal angle.
+    Metropolis,
+    /// Single cluster updates by reflecting spins about a random axis.
+    Wolff,
+    /// Multi cluster updates of the whole lattice by reflecting spins about a random axis.
+    SwendsenWang,
+    /// Single spin updates drawing each angle from the distribution of its local field.
+    HeatBath,
+    /// Global updates of all angles along molecular dynamics trajectories with gaussian momenta.
+    Hybrid,
+    /// Microcanonical single spin updates whose energy change is exchanged with Creutz demons.
+    Demon,
+    /// Overdamped Langevin dynamics of the angles, where every sweep advances the time by dt.
+    Langevin,
+    /// Random sequential Glauber dynamics, where every sweep advances the time by dt.
+    Glauber,
+    /// Single spin updates of all sites of one color of the checkerboard at once in parallel.
+    Checkerboard,
+    /// Single spin updates of four or, in single precision, eight sites of one color at once in
+    /// the SIMD lanes.
+    Vectorized,
+}
+
+/// The algorithm parameters which can be selected at run time.
+#[derive(Clone, Copy, clap::Args)]
+pub struct Parameters {
+    /// The Monte Carlo method used to update the lattice.
+    #[arg(short = 'a', long = "algorithm", value_enum, default_value_t = Method::Metropolis)]
+    pub method: Method,
+
+    /// The number of overrelaxation sweeps performed before every Metropolis sweep.
+    #[arg(short = 'k', long = "overrelaxation", default_value_t = 0)]
+    pub overrelaxation: usize,
+
+    /// The target acceptance rate towards which the Metropolis proposal width is tuned. If not
+    /// given, angles are proposed uniformly on the unit circle.
+    #[arg(long = "acceptance")]
+    pub acceptance: Option<f64>,
+
+    /// The number of thermalization sweeps used to tune the Metropolis proposal width and to
+    /// bring the lattice to its temperature before the demons are created.
+    #[arg(long = "thermalization", default_value_t = 10_000)]
+    pub thermalization: usize,
+
+    /// Enables replica exchange between neighbouring temperatures after the given number of
+    /// sweeps. Otherwise every temperature is simulated as an independent chain.
+    #[arg(short = 'x', long = "tempering")]
+    pub tempering: Option<usize>,
+
+    /// The length of a hybrid Monte Carlo trajectory in molecular dynamics time units.
+    #[arg(long = "trajectory", default_value_t = 1.0)]
+    pub trajectory: f64,
+
+    /// The step size of the leapfrog integrator of hybrid Monte Carlo.
+    #[arg(long = "step_size", default_value_t = 0.1)]
+    pub step_size: f64,
+
+    /// The number of demons exchanging energy with the lattice in the microcanonical method.
+    #[arg(long = "demons", default_value_t = 1)]
+    pub demons: usize,
+
+    /// The time step of the Langevin and Glauber dynamics.
+    #[arg(long = "dt", default_value_t = 0.01)]
+    pub dt: f64,
+
+    /// The damping of the Langevin dynamics and the mean time between two Glauber updates of a
+    /// spin.
+    #[arg(long = "damping", default_value_t = 1.0)]
+    pub damping: f64,
+
+    /// The temperature the lattice is quenched to for the real time dynamics.
+    #[arg(long = "dynamics_temperature", default_value_t = 0.3)]
+    pub dynamics_temperature: f64,
+
+    /// The total time of the real time dynamics after the quench.
+    #[arg(long = "dynamics_time", default_value_t = 100.0)]
+    pub dynamics_time: f64,
+
+    /// The time between two snapshots of the real time dynamics.
+    #[arg(long = "snapshot_interval", default_value_t = 1.0)]
+    pub snapshot_interval: f64,
+}
+
+impl Parameters {
+    /// Returns the time a single sweep advances. Sweeps of the dynamics methods advance the
+    /// time by dt, all other sweeps count as one unit of time.
+    pub fn time_step(&self) -> f64 {
+        match self.method {
+            Method::Langevin | Method::Glauber => self.dt,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Holds the energy and magnetization series of a simulation together with the coupling and
+/// current series of the helicity modulus and the vortex density series of lattices with vortex
+/// detection, which only chains measuring them fill, and the acceptance rate of the proposed
+/// updates. Rejection free algorithms have an acceptance rate of one. Algorithms which integrate
+/// trajectories additionally report the violation of their Hamiltonian and microcanonical
+/// algorithms the temperature they measured.
+pub struct Series {
+    pub energies: Vec<f64>,
+    pub magnets: Vec<f64>,
+    pub couplings: Vec<f64>,
+    pub currents: Vec<f64>,
+    pub vortices: Vec<f64>,
+    pub acceptance: f64,
+    pub violation: Option<Violation>,
+    pub temperature: Option<f64>,
+}
+
+/// Holds the state of a Markov chain which persists across the calls of `Algorithm::simulate`,
+/// so that repeated short calls continue the chain instead of preparing it anew.
+#[derive(Clone, Default)]
+pub struct Chain {
+    /// The Metropolis proposal width at unit temperature, which is tuned during the first call
+    /// and scaled to the temperature of every later call.
+    pub width: Option<f64>,
+    /// The energies of the Creutz demons, which are created during the first call.
+    pub demons: Vec<f64>,
+    /// Whether the helicity terms and the vortex density are measured after every sweep. They
+    /// have no cheap update, so only the production runs whose series are analyzed enable them.
+    pub observables: bool,
+}
 
 /// Algorithm is the supertrait for all concrete Monte Carlo algorithms
 pub trait Algorithm {
-    /// Simulates the system using the given random number generator for the given
-    /// number of sweeps. Returns the energy and magnetization after said sweeps.
-    fn simulate(&mut self, rng: &mut fastrand::Rng, sweeps: usize) -> (Vec<f64>, Vec<f64>);
+    /// Simulates the system with the given parameters using the given random number generator
+    /// for the given number of sweeps, continuing the given chain. Returns the energy and
+    /// magnetization after said sweeps together with the acceptance rate.
+    fn simulate(
+        &mut self,
+        parameters: Parameters,
+        chain: &mut Chain,
+        rng: &mut fastrand::Rng,
+        sweeps: usize,
+    ) -> Series;
+}
+
+impl<T> Algorithm for T
+where
+    T: Lattice
+        + Metropolis
+        + Wolff
+        + SwendsenWang
+        + HeatBath
+        + HybridMonteCarlo
+        + Demon
+        + Dynamics
+        + Checkerboard
+        + Vectorized,
+{
+    /// Runs the selected algorithm on the lattice for the given number of sweeps.
+    /// Returns the energy and magnetization observables and the acceptance rate.
+    fn simulate(
+        &mut self,
+        parameters: Parameters,
+        chain: &mut Chain,
+        rng: &mut fastrand::Rng,
+        sweeps: usize,
+    ) -> Series {
+        match parameters.method {
+            Method::Metropolis => self.metropolis_hastings(
+                rng,
+                sweeps,
+                chain.observables,
+                parameters.overrelaxation,
+                parameters
+                    .acceptance
+                    .map(|x| (x, parameters.thermalization)),
+                &mut chain.width,
+            ),
+            Method::Wolff => self.wolff(rng, sweeps, chain.observables),
+            Method::SwendsenWang => self.swendsen_wang(rng, sweeps, chain.observables),
+            Method::HeatBath => self.heat_bath(rng, sweeps, chain.observables),
+            Method::Hybrid => self.hybrid_monte_carlo(
+                rng,
+                sweeps,
+                chain.observables,
+                parameters.trajectory,
+                parameters.step_size,
+            ),
+            Method::Demon => self.demon(
+                rng,
+                sweeps,
+                chain.observables,
+                &mut chain.demons,
+                parameters.demons,
+                parameters.thermalization,
+            ),
+            Method::Langevin => {
+                self.langevin(rng, sweeps, chain.observables, parameters.dt, parameters.damping)
+            }
+            Method::Glauber => {
+                self.glauber(rng, sweeps, chain.observables, parameters.dt, parameters.damping)
+            }
+            Method::Checkerboard => self.checkerboard(rng, sweeps, chain.observables),
+            Method::Vectorized => self.vectorized(rng, sweeps, chain.observables),
+        }
+    }
+}
+
+/// Performs the given number of sweeps on the lattice and collects the observables after each
+/// sweep. The sweep closure must return the energy and magnetization delta of the sweep, where
+/// the magnetization delta is split into its (cos, sin) components. The helicity terms and the
+/// vortex density are only measured if observables is set and stay empty otherwise. The
+/// acceptance rate of the returned series is one and must be overwritten by algorithms which
+/// reject updates.
+pub fn measure<T, F>(lattice: &mut T, sweeps: usize, observables: bool, mut sweep: F) -> Series
+where
+    T: Lattice,
+    F: FnMut(&mut T) -> (f64, (f64, f64)),
+{
+    // Prepare results vectors
+    let mut energies = Vec::<f64>::with_capacity(sweeps);
+    let mut magnets = Vec::<f64>::with_capacity(sweeps);
+    let mut couplings = Vec::<f64>::with_capacity(if observables { sweeps } else { 0 });
+    let mut currents = Vec::<f64>::with_capacity(if observables { sweeps } else { 0 });
+    let mut vortices = Vec::<f64>::new();
+
+    // Calculate initial observables and sweeps over lattice
+    let (mut cur_energy, mut cur_magnetization) = (lattice.energy(), lattice.magnetization());
+    for _ in 0..sweeps {
+        // Perform sweep and collect running observables
+        let (chg_energy, chg_magnetization) = sweep(lattice);
+        cur_energy += chg_energy;
+        cur_magnetization.0 += chg_magnetization.0;
+        cur_magnetization.1 += chg_magnetization.1;
+
+        // Push current observables to results
+        energies.push(lattice.normalize_per_spin(cur_energy));
+        magnets.push(lattice.normalize_per_spin(f64::sqrt(
+            cur_magnetization.0.powi(2) + cur_magnetization.1.powi(2),
+        )));
+
+        // The helicity terms and the vortex density have no cheap update and are measured anew
+        if observables {
+            let (coupling, current) = lattice.helicity();
+            couplings.push(coupling);
+            currents.push(current);
+            if let Some(density) = lattice.vortex_density() {
+                vortices.push(density);
+            }
+        }
+    }
+
+    // Return results
+    Series {
+        energies,
+        magnets,
+        couplings,
+        currents,
+        vortices,
+        acceptance: 1.0,
+        violation: None,
+        temperature: None,
+    }
 }
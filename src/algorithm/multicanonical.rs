@@ -0,0 +1,101 @@
+use crate::algorithm::{measure, Algorithm, Series};
+use crate::analysis::DensityOfStates;
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+
+pub trait Multicanonical: Algorithm {
+    /// Perform a single sweep over all lattice sites accepting proposals with the probability
+    /// g(E_old) / g(E_new), so that every energy bin is visited equally often. Proposals into
+    /// bins without states are rejected. Takes and updates the current total energy. Returns the
+    /// number of accepted proposals and the energy and magnetization delta. The magnetization
+    /// delta is split into its (cos, sin) components.
+    fn multicanonical_sweep(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        energy: &mut f64,
+        density: &DensityOfStates,
+    ) -> (usize, f64, (f64, f64));
+
+    /// Run simulation with the multicanonical weights 1/g(E) of the given density of states for
+    /// the given number of sweeps. A lattice whose energy lies outside the range of the density
+    /// is driven into it first. Returns the energy and magnetization observables and the
+    /// acceptance rate.
+    fn multicanonical(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        density: &DensityOfStates,
+        sweeps: usize,
+    ) -> Series;
+}
+
+impl<T> Multicanonical for T
+where
+    T: Lattice,
+{
+    fn multicanonical_sweep(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        energy: &mut f64,
+        density: &DensityOfStates,
+    ) -> (usize, f64, (f64, f64)) {
+        // Prepare change variables
+        let mut accepted = 0;
+        let (mut chg_energy, mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0, 0.0);
+
+        // Go over all lattice sites
+        for i in 0..self.sites() {
+            // Generate random angle [0,2pi) and calculate difference in e and m
+            let angle = rng.f64() * MAX_ANGLE;
+            let diff_energy = self.energy_diff(i, angle);
+            let current = density.bin(*energy);
+            let next = density.bin(*energy + diff_energy);
+
+            // Check acceptance ratio for the density of states. Proposals leaving the range or
+            // entering bins without states are always rejected. A lattice starting outside the
+            // range or in a bin without states escapes with the first valid proposal towards it.
+            let accept = match (current, next) {
+                (_, Some(next)) if !density.ln_g[next].is_finite() => false,
+                (Some(current), Some(next)) => {
+                    !density.ln_g[current].is_finite()
+                        || f64::exp(density.ln_g[current] - density.ln_g[next]) > rng.f64()
+                }
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (None, None) => density.distance(*energy + diff_energy) < density.distance(*energy),
+            };
+
+            // Update observables and spin if change is accepted
+            if accept {
+                let (diff_magnet_cos, diff_magnet_sin) = self.magnetization_diff(i, angle);
+                accepted += 1;
+                *energy += diff_energy;
+                chg_energy += diff_energy;
+                chg_magnet_cos += diff_magnet_cos;
+                chg_magnet_sin += diff_magnet_sin;
+                self.update_angle(i, angle);
+            }
+        }
+
+        // Return acceptance count and change in observables
+        (accepted, chg_energy, (chg_magnet_cos, chg_magnet_sin))
+    }
+
+    fn multicanonical(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        density: &DensityOfStates,
+        sweeps: usize,
+    ) -> Series {
+        let (mut accepted, mut energy) = (0, self.energy());
+        let mut series = measure(self, sweeps, false, |lattice| {
+            let (count, chg_energy, chg_magnetization) =
+                lattice.multicanonical_sweep(rng, &mut energy, density);
+            accepted += count;
+            (chg_energy, chg_magnetization)
+        });
+
+        // Acceptance rate over the measured sweeps
+        series.acceptance = accepted as f64 / (sweeps * self.sites()).max(1) as f64;
+        series
+    }
+}
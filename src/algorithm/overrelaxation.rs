@@ -0,0 +1,39 @@
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+
+pub trait Overrelaxation {
+    /// Perform a single microcanonical sweep over all lattice sites by reflecting each spin
+    /// about its local field. The energy is conserved, so only the magnetization delta is
+    /// returned. The magnetization delta is split into its (cos, sin) components.
+    fn overrelax(&mut self) -> (f64, f64);
+}
+
+impl<T> Overrelaxation for T
+where
+    T: Lattice,
+{
+    fn overrelax(&mut self) -> (f64, f64) {
+        // Prepare change variables
+        let (mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0);
+
+        // Go over all lattice sites
+        for i in 0..self.sites() {
+            // Spins without a local field can not be reflected
+            let (field_cos, field_sin) = self.local_field(i);
+            if field_cos == 0.0 && field_sin == 0.0 {
+                continue;
+            }
+
+            // Reflect spin about local field and update magnetization and spin
+            let angle =
+                (2.0 * f64::atan2(field_sin, field_cos) - self.angle(i)).rem_euclid(MAX_ANGLE);
+            let (diff_magnet_cos, diff_magnet_sin) = self.magnetization_diff(i, angle);
+            chg_magnet_cos += diff_magnet_cos;
+            chg_magnet_sin += diff_magnet_sin;
+            self.update_angle(i, angle);
+        }
+
+        // Return change in magnetization
+        (chg_magnet_cos, chg_magnet_sin)
+    }
+}
@@ -0,0 +1,75 @@
+use crate::algorithm::{Algorithm, Chain, Parameters};
+use crate::lattice::Lattice;
+
+/// The temperature schedules T(t) a quench can follow.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Schedule {
+    /// Linear ramp from the first to the last temperature.
+    Linear,
+    /// Exponential decay from the first to the last temperature.
+    Exponential,
+    /// Linear ramps between all given temperatures, each taking the same time.
+    Piecewise,
+}
+
+impl Schedule {
+    /// Returns the temperature after the fraction s in [0, 1] of the quench time. The quench
+    /// runs from the first to the last of the given temperatures, of which there must be at
+    /// least two.
+    pub fn temperature(self, knots: &[f64], s: f64) -> f64 {
+        let (start, end) = (knots[0], knots[knots.len() - 1]);
+        match self {
+            Schedule::Linear => start + (end - start) * s,
+            Schedule::Exponential => start * (end / start).powf(s),
+            Schedule::Piecewise => {
+                let x = s.clamp(0.0, 1.0) * (knots.len() - 1) as f64;
+                let k = usize::min(x as usize, knots.len() - 2);
+                knots[k] + (knots[k + 1] - knots[k]) * (x - k as f64)
+            }
+        }
+    }
+}
+
+/// Quenches the lattice within the given number of sweeps, where the schedule returns the
+/// temperature after the fraction s in [0, 1] of the quench. The lattice is first thermalized at
+/// the initial temperature for the thermalization sweeps of the parameters, afterwards the
+/// temperature is updated via `Lattice::set_beta` before every sweep. The lattice is recorded at
+/// the given number of equally spaced sweeps, or after every sweep if the quench is shorter,
+/// where every sweep advances the time by the time step of the parameters. Returns the records,
+/// which are taken from the lattice, the time and the temperature.
+pub fn quench<L, S, R, F>(
+    rng: &mut fastrand::Rng,
+    parameters: Parameters,
+    lattice: &mut L,
+    schedule: S,
+    sweeps: usize,
+    records: usize,
+    mut record: F,
+) -> Vec<R>
+where
+    L: Lattice,
+    S: Fn(f64) -> f64,
+    F: FnMut(&L, f64, f64) -> R,
+{
+    let time_step = parameters.time_step();
+    let mut results = Vec::with_capacity(usize::min(records, sweeps));
+
+    // Thermalize lattice at the initial temperature
+    let mut chain = Chain::default();
+    lattice.set_beta(schedule(0.0).recip());
+    lattice.simulate(parameters, &mut chain, rng, parameters.thermalization);
+
+    for n in 1..=sweeps {
+        // Cool lattice and advance it by a single sweep
+        let temperature = schedule(n as f64 / sweeps as f64);
+        lattice.set_beta(temperature.recip());
+        lattice.simulate(parameters, &mut chain, rng, 1);
+
+        // Record once the next of the equally spaced sweeps is reached
+        if n * records / sweeps > results.len() {
+            results.push(record(lattice, n as f64 * time_step, temperature));
+        }
+    }
+
+    results
+}
@@ -0,0 +1,146 @@
+use crate::algorithm::wolff::reflect;
+use crate::algorithm::{measure, Algorithm, Series};
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of consecutive sites whose bonds are activated by a single rayon task.
+const CHUNK: usize = 1024;
+
+pub trait SwendsenWang: Algorithm {
+    /// Decomposes the whole lattice into embedded Ising clusters with respect to the given axis
+    /// angle and reflects each cluster with probability 1/2. The parent and projection buffers
+    /// must have the length of the lattice. Returns the energy and magnetization delta. The
+    /// magnetization delta is split into its (cos, sin) components.
+    fn multi_cluster(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        axis: f64,
+        parent: &mut [AtomicUsize],
+        projection: &mut [f64],
+    ) -> (f64, (f64, f64));
+
+    /// Run simulation using the Swendsen Wang multi cluster algorithm for the given number of
+    /// sweeps. Returns the energy and magnetization observables.
+    fn swendsen_wang(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        sweeps: usize,
+        observables: bool,
+    ) -> Series;
+}
+
+/// Finds the root of the cluster site i belongs to. Concurrently halves the path to the root.
+fn find(parent: &[AtomicUsize], mut i: usize) -> usize {
+    loop {
+        let p = parent[i].load(Ordering::Relaxed);
+        if p == i {
+            return i;
+        }
+
+        let grand = parent[p].load(Ordering::Relaxed);
+        let _ = parent[i].compare_exchange_weak(p, grand, Ordering::Relaxed, Ordering::Relaxed);
+        i = p;
+    }
+}
+
+/// Merges the clusters of site a and b. Roots are always linked towards the smaller index so
+/// that concurrent unions can never create cycles.
+fn union(parent: &[AtomicUsize], a: usize, b: usize) {
+    loop {
+        let (a, b) = (find(parent, a), find(parent, b));
+        if a == b {
+            return;
+        }
+
+        let (low, high) = (a.min(b), a.max(b));
+        if parent[high]
+            .compare_exchange(high, low, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            return;
+        }
+    }
+}
+
+impl<T> SwendsenWang for T
+where
+    T: Lattice,
+{
+    fn multi_cluster(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        axis: f64,
+        parent: &mut [AtomicUsize],
+        projection: &mut [f64],
+    ) -> (f64, (f64, f64)) {
+        // Reset clusters and project spins onto the axis in parallel
+        let lattice = &*self;
+        parent
+            .par_iter_mut()
+            .zip(projection.par_iter_mut())
+            .enumerate()
+            .for_each(|(i, (p, r))| {
+                *p.get_mut() = i;
+                *r = f64::cos(lattice.angle(i) - axis);
+            });
+
+        // Activate bonds and label clusters in parallel with an independent RNG per chunk
+        let (parent, projection) = (&*parent, &*projection);
+        let seeds = (0..lattice.sites().div_ceil(CHUNK))
+            .map(|_| rng.u64(..))
+            .collect::<Vec<_>>();
+        seeds.into_par_iter().enumerate().for_each(|(c, seed)| {
+            let mut rng = fastrand::Rng::with_seed(seed);
+            for i in (c * CHUNK)..usize::min((c + 1) * CHUNK, lattice.sites()) {
+                // Each bond is only considered from its lower index site. Twisted boundaries are
+                // rejected for cluster updates, so the phase is 0 or π and an antiperiodic bond
+                // inverts the projection of the neighbour
+                for (j, phase) in lattice.neighbours(i).filter(|(j, _)| *j > i) {
+                    let bond = -2.0 * lattice.beta() * projection[i] * projection[j] * phase.cos();
+                    if -f64::exp_m1(f64::min(0.0, bond)) > rng.f64() {
+                        union(parent, i, j);
+                    }
+                }
+            }
+        });
+
+        // Decide for each cluster root whether the cluster is flipped
+        let flip = (0..self.sites()).map(|_| rng.bool()).collect::<Vec<_>>();
+
+        // Prepare change variables
+        let (mut chg_energy, mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0, 0.0);
+
+        // Reflect all spins of flipped clusters and update observables
+        for i in 0..self.sites() {
+            if flip[find(parent, i)] {
+                let angle = reflect(self.angle(i), axis);
+                let (diff_magnet_cos, diff_magnet_sin) = self.magnetization_diff(i, angle);
+                chg_energy += self.energy_diff(i, angle);
+                chg_magnet_cos += diff_magnet_cos;
+                chg_magnet_sin += diff_magnet_sin;
+                self.update_angle(i, angle);
+            }
+        }
+
+        // Return change in observables
+        (chg_energy, (chg_magnet_cos, chg_magnet_sin))
+    }
+
+    fn swendsen_wang(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        sweeps: usize,
+        observables: bool,
+    ) -> Series {
+        // Buffers are reused across all sweeps
+        let mut parent = (0..self.sites()).map(AtomicUsize::new).collect::<Vec<_>>();
+        let mut projection = vec![0.0; self.sites()];
+
+        measure(self, sweeps, observables, |lattice| {
+            let axis = rng.f64() * MAX_ANGLE;
+            lattice.multi_cluster(rng, axis, &mut parent, &mut projection)
+        })
+    }
+}
@@ -0,0 +1,122 @@
+use crate::algorithm::hybrid::Violation;
+use crate::algorithm::{Algorithm, Chain, Parameters, Series};
+use crate::lattice::Lattice;
+use rayon::prelude::*;
+
+/// Runs parallel tempering on the given replicas, which must be ordered by temperature. All
+/// replicas are advanced in parallel for the given interval of sweeps, after which neighbouring
+/// temperatures alternately attempt to swap their configurations. The betas are exchanged via
+/// `Lattice::set_beta` and the replicas are reordered, so that on return the replicas are still
+/// ordered by temperature. Returns the series measured at every temperature and the swap
+/// acceptance rate of every pair of neighbouring temperatures.
+pub fn parallel_tempering<L>(
+    rng: &mut fastrand::Rng,
+    parameters: Parameters,
+    replicas: &mut [L],
+    sweeps: usize,
+    interval: usize,
+) -> (Vec<Series>, Vec<f64>)
+where
+    L: Lattice + Send,
+{
+    // A tuned proposal width would no longer match the temperature after a swap
+    let parameters = Parameters {
+        acceptance: None,
+        ..parameters
+    };
+
+    // Prepare series per temperature and swap statistics per pair
+    let mut results = (0..replicas.len())
+        .map(|_| Series {
+            energies: Vec::with_capacity(sweeps),
+            magnets: Vec::with_capacity(sweeps),
+            couplings: Vec::with_capacity(sweeps),
+            currents: Vec::with_capacity(sweeps),
+            vortices: Vec::new(),
+            acceptance: 0.0,
+            violation: None,
+            temperature: None,
+        })
+        .collect::<Vec<_>>();
+    let (pairs, interval) = (replicas.len().saturating_sub(1), interval.max(1));
+    let (mut attempts, mut accepted) = (vec![0_usize; pairs], vec![0_usize; pairs]);
+
+    // Every replica continues its own chain with all observables, which travels with it on a swap
+    let chain = Chain {
+        observables: true,
+        ..Chain::default()
+    };
+    let mut chains = vec![chain; replicas.len()];
+
+    for (n, start) in (0..sweeps).step_by(interval).enumerate() {
+        // Advance all replicas in parallel with independent random number generators
+        let length = usize::min(interval, sweeps - start);
+        let seeds = replicas.iter().map(|_| rng.u64(..)).collect::<Vec<_>>();
+        let chunks = replicas
+            .par_iter_mut()
+            .zip(chains.par_iter_mut())
+            .zip(seeds)
+            .map(|((lattice, chain), seed)| {
+                let mut rng = fastrand::Rng::with_seed(seed);
+                let series = lattice.simulate(parameters, chain, &mut rng, length);
+                (series, lattice.energy())
+            })
+            .collect::<Vec<_>>();
+
+        // Append series of each replica to the series of its current temperature
+        let mut energies = Vec::with_capacity(chunks.len());
+        for (result, (mut series, energy)) in results.iter_mut().zip(chunks) {
+            result.energies.append(&mut series.energies);
+            result.magnets.append(&mut series.magnets);
+            result.couplings.append(&mut series.couplings);
+            result.currents.append(&mut series.currents);
+            result.vortices.append(&mut series.vortices);
+            result.acceptance += series.acceptance * length as f64;
+            if let Some(violation) = series.violation {
+                let total = result.violation.get_or_insert_with(Violation::default);
+                total.mean += violation.mean * length as f64;
+                total.sqr_mean += violation.sqr_mean * length as f64;
+                total.exp_mean += violation.exp_mean * length as f64;
+            }
+            if let Some(temperature) = series.temperature {
+                *result.temperature.get_or_insert(0.0) += temperature * length as f64;
+            }
+            energies.push(energy);
+        }
+
+        // Attempt swaps of even pairs and odd pairs in alternating intervals
+        for k in ((n % 2)..pairs).step_by(2) {
+            let (low, high) = (replicas[k].beta(), replicas[k + 1].beta());
+            let delta = (low - high) * (energies[k] - energies[k + 1]);
+
+            attempts[k] += 1;
+            if f64::exp(f64::min(0.0, delta)) > rng.f64() {
+                accepted[k] += 1;
+                replicas[k].set_beta(high);
+                replicas[k + 1].set_beta(low);
+                replicas.swap(k, k + 1);
+                chains.swap(k, k + 1);
+            }
+        }
+    }
+
+    // Normalize acceptance rates of replicas and swaps
+    for result in results.iter_mut() {
+        result.acceptance /= sweeps.max(1) as f64;
+        if let Some(violation) = result.violation.as_mut() {
+            violation.mean /= sweeps.max(1) as f64;
+            violation.sqr_mean /= sweeps.max(1) as f64;
+            violation.exp_mean /= sweeps.max(1) as f64;
+        }
+        if let Some(temperature) = result.temperature.as_mut() {
+            *temperature /= sweeps.max(1) as f64;
+        }
+    }
+    let rates = attempts
+        .into_iter()
+        .zip(accepted)
+        .map(|(attempts, accepted)| accepted as f64 / attempts.max(1) as f64)
+        .collect();
+
+    (results, rates)
+}
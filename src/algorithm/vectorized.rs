@@ -0,0 +1,244 @@
+use crate::algorithm::checkerboard::coloring;
+use crate::algorithm::{measure, Algorithm, Series};
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub};
+use wide::{f32x8, f64x4, u64x4, CmpGt};
+
+/// A xoshiro256+ random number generator running four independent streams in SIMD lanes.
+pub struct SimdRng([u64x4; 4]);
+
+impl SimdRng {
+    /// Seeds the states of all streams from the given random number generator.
+    pub fn new(rng: &mut fastrand::Rng) -> Self {
+        Self([(); 4].map(|_| u64x4::from([(); 4].map(|_| rng.u64(1..)))))
+    }
+
+    /// Returns the next 64 random bits of every stream.
+    fn next(&mut self) -> u64x4 {
+        let [s0, s1, s2, s3] = &mut self.0;
+        let result = *s0 + *s3;
+
+        // Advance the state of all streams
+        let t = *s1 << 17;
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = (*s3 << 45) | (*s3 >> 19);
+        result
+    }
+
+    /// Draws a uniformly distributed number in [0, 1) in every lane.
+    pub fn f64x4(&mut self) -> f64x4 {
+        // Use the upper 52 bits as the mantissa of a number in [1, 2)
+        let bits: u64x4 = (self.next() >> 12) | u64x4::splat(0x3FF0_0000_0000_0000);
+        f64x4::from(bits.to_array().map(f64::from_bits)) - f64x4::ONE
+    }
+
+    /// Draws a uniformly distributed number in [0, 1) in every lane, where every stream fills
+    /// two lanes with the two halves of its random bits.
+    pub fn f32x8(&mut self) -> f32x8 {
+        // Use the upper 23 bits of each half as the mantissa of a number in [1, 2)
+        let bits = self.next().to_array();
+        let halves: [u32; 8] = std::array::from_fn(|k| (bits[k / 2] >> (32 * (k % 2))) as u32);
+        f32x8::from(halves.map(|half| f32::from_bits((half >> 9) | 0x3F80_0000))) - f32x8::ONE
+    }
+}
+
+/// A SIMD vector of N lanes in which the vectorized sweep calculates.
+trait Lanes<const N: usize>
+where
+    Self: Copy + AddAssign + Neg<Output = Self>,
+    Self: Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self>,
+{
+    /// The vector of zeros in all lanes.
+    const ZERO: Self;
+
+    /// Converts double precision values into the lanes.
+    fn from_f64(values: [f64; N]) -> Self;
+
+    /// Converts the lanes back into double precision values.
+    fn to_f64(self) -> [f64; N];
+
+    /// Draws a uniformly distributed number in [0, 1) in every lane.
+    fn uniform(rng: &mut SimdRng) -> Self;
+
+    /// Calculates the sine and cosine in every lane.
+    fn sin_cos(self) -> (Self, Self);
+
+    /// Calculates the exponential in every lane.
+    fn exp(self) -> Self;
+
+    /// Returns the bit mask of the lanes that are greater than those of the other vector.
+    fn greater(self, other: Self) -> i32;
+}
+
+impl Lanes<4> for f64x4 {
+    const ZERO: Self = f64x4::ZERO;
+
+    fn from_f64(values: [f64; 4]) -> Self {
+        f64x4::from(values)
+    }
+
+    fn to_f64(self) -> [f64; 4] {
+        self.to_array()
+    }
+
+    fn uniform(rng: &mut SimdRng) -> Self {
+        rng.f64x4()
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        f64x4::sin_cos(self)
+    }
+
+    fn exp(self) -> Self {
+        f64x4::exp(self)
+    }
+
+    fn greater(self, other: Self) -> i32 {
+        self.cmp_gt(other).move_mask()
+    }
+}
+
+impl Lanes<8> for f32x8 {
+    const ZERO: Self = f32x8::ZERO;
+
+    fn from_f64(values: [f64; 8]) -> Self {
+        f32x8::from(values.map(|value| value as f32))
+    }
+
+    fn to_f64(self) -> [f64; 8] {
+        self.to_array().map(f64::from)
+    }
+
+    fn uniform(rng: &mut SimdRng) -> Self {
+        rng.f32x8()
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        f32x8::sin_cos(self)
+    }
+
+    fn exp(self) -> Self {
+        f32x8::exp(self)
+    }
+
+    fn greater(self, other: Self) -> i32 {
+        self.cmp_gt(other).move_mask()
+    }
+}
+
+/// Performs the vectorized sweep with N sites of the same color in the lanes of the vector V.
+fn lanes_sweep<T, V, const N: usize>(
+    lattice: &mut T,
+    rng: &mut SimdRng,
+    colors: &[Vec<usize>],
+) -> (usize, f64, (f64, f64))
+where
+    T: Lattice,
+    V: Lanes<N>,
+{
+    // Prepare change variables
+    let mut accepted = 0;
+    let (mut chg_energy, mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0, 0.0);
+    let beta = V::from_f64([lattice.beta(); N]);
+
+    for chunk in colors.iter().flat_map(|sites| sites.chunks(N)) {
+        // Fill missing lanes with the first site and mask them out
+        let sites: [usize; N] = std::array::from_fn(|k| chunk[k.min(chunk.len() - 1)]);
+        let active = (1 << chunk.len()) - 1;
+
+        // Sum up the local fields of all lanes, lanes without further neighbours add zero
+        let (field_cos, field_sin) = {
+            let (mut field_cos, mut field_sin) = (V::ZERO, V::ZERO);
+            let mut neighbours = sites.map(|i| lattice.neighbours(i));
+            loop {
+                let next = neighbours.each_mut().map(|n| n.next());
+                if next.iter().all(Option::is_none) {
+                    break (field_cos, field_sin);
+                }
+                let weight = V::from_f64(next.map(|j| j.map_or(0.0, |_| 1.0)));
+                let angles = next.map(|j| j.map_or(0.0, |(j, phase)| lattice.angle(j) + phase));
+                let (sin, cos) = V::from_f64(angles).sin_cos();
+                field_cos += weight * cos;
+                field_sin += weight * sin;
+            }
+        };
+
+        // Propose angles rounded to their stored representation and calculate energy and
+        // magnetization differences lane wise
+        let (old_sin, old_cos) = V::from_f64(sites.map(|i| lattice.angle(i))).sin_cos();
+        let angles = (V::uniform(rng) * V::from_f64([MAX_ANGLE; N])).to_f64();
+        let angles = angles.map(|angle| lattice.round_angle(angle));
+        let (new_sin, new_cos) = V::from_f64(angles).sin_cos();
+        let diff_energy = (old_cos - new_cos) * field_cos + (old_sin - new_sin) * field_sin;
+        let (diff_cos, diff_sin) = (new_cos - old_cos, new_sin - old_sin);
+
+        // Accept lanes whose uniform number lies below exp(-beta * dE)
+        let mask = (-beta * diff_energy).exp().greater(V::uniform(rng)) & active;
+        let diff_energy = diff_energy.to_f64();
+        let (diff_cos, diff_sin) = (diff_cos.to_f64(), diff_sin.to_f64());
+        for k in (0..N).filter(|k| mask & (1 << k) != 0) {
+            accepted += 1;
+            chg_energy += diff_energy[k];
+            chg_magnet_cos += diff_cos[k];
+            chg_magnet_sin += diff_sin[k];
+            lattice.update_angle(sites[k], angles[k]);
+        }
+    }
+
+    // Return acceptance count and change in observables
+    (accepted, chg_energy, (chg_magnet_cos, chg_magnet_sin))
+}
+
+pub trait Vectorized: Algorithm {
+    /// Perform a single Metropolis sweep over all lattice sites, where LANES sites of the same
+    /// color are proposed, evaluated and accepted at once in the SIMD lanes. The random numbers,
+    /// the local fields, the energy differences and the acceptance probabilities are all
+    /// calculated lane wise. Returns the number of accepted proposals and the energy and
+    /// magnetization delta. The magnetization delta is split into its (cos, sin) components.
+    fn vectorized_sweep(
+        &mut self,
+        rng: &mut SimdRng,
+        colors: &[Vec<usize>],
+    ) -> (usize, f64, (f64, f64));
+
+    /// Run simulation using the vectorized Metropolis algorithm for the given number of sweeps.
+    /// Returns the energy and magnetization observables and the acceptance rate.
+    fn vectorized(&mut self, rng: &mut fastrand::Rng, sweeps: usize, observables: bool) -> Series;
+}
+
+impl<T> Vectorized for T
+where
+    T: Lattice,
+{
+    fn vectorized_sweep(
+        &mut self,
+        rng: &mut SimdRng,
+        colors: &[Vec<usize>],
+    ) -> (usize, f64, (f64, f64)) {
+        // Single precision spins fit twice as many lanes into a vector as double precision ones
+        match T::LANES {
+            8 => lanes_sweep::<T, f32x8, 8>(self, rng, colors),
+            _ => lanes_sweep::<T, f64x4, 4>(self, rng, colors),
+        }
+    }
+
+    fn vectorized(&mut self, rng: &mut fastrand::Rng, sweeps: usize, observables: bool) -> Series {
+        let (colors, mut rng) = (coloring(self), SimdRng::new(rng));
+        let mut accepted = 0;
+        let mut series = measure(self, sweeps, observables, |lattice| {
+            let (count, chg_energy, chg_magnetization) =
+                lattice.vectorized_sweep(&mut rng, &colors);
+            accepted += count;
+            (chg_energy, chg_magnetization)
+        });
+
+        // Acceptance rate over the measured sweeps
+        series.acceptance = accepted as f64 / (sweeps * self.sites()).max(1) as f64;
+        series
+    }
+}
@@ -0,0 +1,125 @@
+use crate::algorithm::Algorithm;
+use crate::analysis::DensityOfStates;
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+
+/// The number of sweeps between two checks for the flatness of the histogram.
+const CHECK_INTERVAL: usize = 100;
+
+pub trait WangLandau: Algorithm {
+    /// Perform a single sweep over all lattice sites accepting proposals with the probability
+    /// g(E_old) / g(E_new). After every proposal the log density and the histogram of the
+    /// current energy bin are updated with the given log modification factor. Takes and updates
+    /// the current total energy.
+    fn wang_landau_sweep(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        energy: &mut f64,
+        density: &mut DensityOfStates,
+        histogram: &mut [usize],
+        ln_f: f64,
+    );
+
+    /// Estimates the density of states between the current energy of the lattice and zero using
+    /// the given number of bins. The log modification factor starts at one and is halved whenever
+    /// the histogram of the visited bins is flat within the given flatness, until it drops
+    /// below the given minimum. Returns the normalized density of states.
+    fn wang_landau(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        bins: usize,
+        flatness: f64,
+        min_factor: f64,
+    ) -> DensityOfStates;
+}
+
+/// Checks if the histogram over the visited bins is flat, that is if the minimum entry is at
+/// least the given fraction of the mean entry.
+fn is_flat(histogram: &[usize], visited: &[bool], flatness: f64) -> bool {
+    let (mut min, mut sum, mut count) = (usize::MAX, 0, 0);
+    for (h, _) in histogram.iter().zip(visited).filter(|(_, v)| **v) {
+        min = usize::min(min, *h);
+        sum += h;
+        count += 1;
+    }
+    count > 0 && min as f64 >= flatness * sum as f64 / count as f64
+}
+
+impl<T> WangLandau for T
+where
+    T: Lattice,
+{
+    fn wang_landau_sweep(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        energy: &mut f64,
+        density: &mut DensityOfStates,
+        histogram: &mut [usize],
+        ln_f: f64,
+    ) {
+        // Go over all lattice sites
+        for i in 0..self.sites() {
+            // Generate random angle [0,2pi) and calculate difference in e
+            let angle = rng.f64() * MAX_ANGLE;
+            let diff_energy = self.energy_diff(i, angle);
+            let current = density.bin(*energy).unwrap_or_default();
+
+            // Proposals leaving the energy range are always rejected
+            if let Some(next) = density.bin(*energy + diff_energy) {
+                if f64::exp(density.ln_g[current] - density.ln_g[next]) > rng.f64() {
+                    *energy += diff_energy;
+                    self.update_angle(i, angle);
+                }
+            }
+
+            // Update density and histogram of the current bin
+            let current = density.bin(*energy).unwrap_or_default();
+            density.ln_g[current] += ln_f;
+            histogram[current] += 1;
+        }
+    }
+
+    fn wang_landau(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        bins: usize,
+        flatness: f64,
+        min_factor: f64,
+    ) -> DensityOfStates {
+        // Energy range spans from the current energy up to infinite temperature
+        let mut energy = self.energy();
+        let mut density = DensityOfStates::new(self.sites(), energy, 0.0, bins);
+
+        // Prepare histogram and the bins which were visited at least once
+        let mut histogram = vec![0; bins];
+        let mut visited = vec![false; bins];
+
+        let mut ln_f = 1.0;
+        while ln_f >= min_factor {
+            for _ in 0..CHECK_INTERVAL {
+                self.wang_landau_sweep(rng, &mut energy, &mut density, &mut histogram, ln_f);
+            }
+
+            // Keep track of all bins which were reachable so far
+            for (v, h) in visited.iter_mut().zip(&histogram) {
+                *v |= *h > 0;
+            }
+
+            // Refine modification factor once the histogram is flat
+            if is_flat(&histogram, &visited, flatness) {
+                histogram.fill(0);
+                ln_f /= 2.0;
+            }
+        }
+
+        // Bins which were never visited have no states
+        for (ln_g, v) in density.ln_g.iter_mut().zip(&visited) {
+            if !v {
+                *ln_g = f64::NEG_INFINITY;
+            }
+        }
+
+        density.normalize();
+        density
+    }
+}
@@ -0,0 +1,128 @@
+use crate::algorithm::{measure, Algorithm, Series};
+use crate::constants::MAX_ANGLE;
+use crate::lattice::Lattice;
+use std::f64::consts::PI;
+
+pub trait Wolff: Algorithm {
+    /// Grows and flips a single cluster by reflecting its spins about the line perpendicular to
+    /// the given axis angle. Takes a zeroed visited buffer and an empty cluster buffer, which are
+    /// both reset before returning. Returns the number of flipped spins and the energy and
+    /// magnetization delta. The magnetization delta is split into its (cos, sin) components.
+    fn cluster(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        axis: f64,
+        visited: &mut [bool],
+        cluster: &mut Vec<usize>,
+    ) -> (usize, f64, (f64, f64));
+
+    /// Performs single cluster updates until at least as many spins as there are lattice sites
+    /// have been flipped and returns the energy and magnetization delta. The magnetization delta
+    /// is split into its (cos, sin) components.
+    fn cluster_sweep(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        visited: &mut [bool],
+        cluster: &mut Vec<usize>,
+    ) -> (f64, (f64, f64));
+
+    /// Run simulation using the Wolff single cluster algorithm for the given number of sweeps.
+    /// Returns the energy and magnetization observables.
+    fn wolff(&mut self, rng: &mut fastrand::Rng, sweeps: usize, observables: bool) -> Series;
+}
+
+/// Reflects the angle about the line perpendicular to the given axis angle.
+pub fn reflect(angle: f64, axis: f64) -> f64 {
+    (PI + 2.0 * axis - angle).rem_euclid(MAX_ANGLE)
+}
+
+impl<T> Wolff for T
+where
+    T: Lattice,
+{
+    fn cluster(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        axis: f64,
+        visited: &mut [bool],
+        cluster: &mut Vec<usize>,
+    ) -> (usize, f64, (f64, f64)) {
+        // Prepare change variables
+        let (mut chg_energy, mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0, 0.0);
+
+        // Pick random seed site for the cluster
+        let seed = rng.usize(0..self.sites());
+        visited[seed] = true;
+        cluster.push(seed);
+
+        // Grow cluster breadth first, the cluster buffer doubles as the queue
+        let mut next = 0;
+        while let Some(&i) = cluster.get(next) {
+            next += 1;
+
+            // Flip spin and update observables
+            let angle = reflect(self.angle(i), axis);
+            let (diff_magnet_cos, diff_magnet_sin) = self.magnetization_diff(i, angle);
+            chg_energy += self.energy_diff(i, angle);
+            chg_magnet_cos += diff_magnet_cos;
+            chg_magnet_sin += diff_magnet_sin;
+            self.update_angle(i, angle);
+
+            // Add aligned neighbours with probability 1 - exp(-2 beta (r * s_i) (r * s_j)) where
+            // s_i is the spin before the flip. After the flip the sign of (r * s_i) is inverted.
+            let projection = f64::cos(self.angle(i) - axis);
+            for (j, phase) in self.neighbours(i) {
+                let bond = 2.0 * self.beta() * projection * f64::cos(self.angle(j) + phase - axis);
+                if !visited[j] && -f64::exp_m1(f64::min(0.0, bond)) > rng.f64() {
+                    visited[j] = true;
+                    cluster.push(j);
+                }
+            }
+        }
+
+        // Reset buffers for next cluster
+        let size = cluster.len();
+        for i in cluster.drain(..) {
+            visited[i] = false;
+        }
+
+        // Return cluster size and change in observables
+        (size, chg_energy, (chg_magnet_cos, chg_magnet_sin))
+    }
+
+    fn cluster_sweep(
+        &mut self,
+        rng: &mut fastrand::Rng,
+        visited: &mut [bool],
+        cluster: &mut Vec<usize>,
+    ) -> (f64, (f64, f64)) {
+        // Prepare change variables
+        let (mut chg_energy, mut chg_magnet_cos, mut chg_magnet_sin) = (0.0, 0.0, 0.0);
+
+        // Flip clusters about random axes until every site was flipped once on average
+        let mut flipped = 0;
+        while flipped < self.sites() {
+            let axis = rng.f64() * MAX_ANGLE;
+            let (size, diff_energy, (diff_magnet_cos, diff_magnet_sin)) =
+                self.cluster(rng, axis, visited, cluster);
+
+            flipped += size;
+            chg_energy += diff_energy;
+            chg_magnet_cos += diff_magnet_cos;
+            chg_magnet_sin += diff_magnet_sin;
+        }
+
+        // Return change in observables
+        (chg_energy, (chg_magnet_cos, chg_magnet_sin))
+    }
+
+    fn wolff(&mut self, rng: &mut fastrand::Rng, sweeps: usize, observables: bool) -> Series {
+        // Buffers are reused across all clusters
+        let mut visited = vec![false; self.sites()];
+        let mut cluster = Vec::with_capacity(self.sites());
+
+        measure(self, sweeps, observables, |lattice| {
+            lattice.cluster_sweep(rng, &mut visited, &mut cluster)
+        })
+    }
+}
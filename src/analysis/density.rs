@@ -0,0 +1,104 @@
+use crate::constants::MAX_ANGLE;
+
+/// Holds the logarithm of the density of states g(E) over equally sized bins of the total
+/// energy. Bins which were never visited have a log density of negative infinity.
+#[derive(Clone)]
+pub struct DensityOfStates {
+    pub sites: usize,
+    pub min: f64,
+    pub max: f64,
+    pub ln_g: Vec<f64>,
+}
+
+impl DensityOfStates {
+    /// Instantiates a flat density of states for the given number of lattice sites over the
+    /// total energy range [min, max] divided into the given number of bins.
+    pub fn new(sites: usize, min: f64, max: f64, bins: usize) -> Self {
+        Self {
+            sites,
+            min,
+            max,
+            ln_g: vec![0.0; bins],
+        }
+    }
+
+    /// Returns the index of the bin the total energy falls into or None if it is out of range.
+    pub fn bin(&self, energy: f64) -> Option<usize> {
+        if energy < self.min || energy > self.max {
+            return None;
+        }
+        let bin = (energy - self.min) / (self.max - self.min) * self.ln_g.len() as f64;
+        Some(usize::min(bin as usize, self.ln_g.len() - 1))
+    }
+
+    /// Returns the distance of the total energy to the range [min, max], which is zero within.
+    pub fn distance(&self, energy: f64) -> f64 {
+        f64::max(0.0, f64::max(self.min - energy, energy - self.max))
+    }
+
+    /// Returns the total energy at the center of the given bin.
+    pub fn energy_of(&self, bin: usize) -> f64 {
+        self.min + (bin as f64 + 0.5) * (self.max - self.min) / self.ln_g.len() as f64
+    }
+
+    /// Normalizes the density of states such that it integrates to the phase space volume
+    /// (2pi)^N of the lattice. States above the maximum energy are neglected.
+    pub fn normalize(&mut self) {
+        let offset = self.sites as f64 * MAX_ANGLE.ln() - log_sum_exp(self.ln_g.iter().copied());
+        for ln_g in self.ln_g.iter_mut() {
+            *ln_g += offset;
+        }
+    }
+
+    /// Calculates the log partition function ln Z at the given temperature.
+    pub fn ln_partition(&self, t: f64) -> f64 {
+        log_sum_exp((0..self.ln_g.len()).map(|b| self.ln_g[b] - self.energy_of(b) / t))
+    }
+
+    /// Calculates the canonical mean of the given function of the total energy at the given
+    /// temperature.
+    pub fn canonical<F>(&self, t: f64, f: F) -> f64
+    where
+        F: Fn(f64) -> f64,
+    {
+        let ln_z = self.ln_partition(t);
+        (0..self.ln_g.len())
+            .filter(|b| self.ln_g[*b].is_finite())
+            .map(|b| {
+                let energy = self.energy_of(b);
+                f(energy) * f64::exp(self.ln_g[b] - energy / t - ln_z)
+            })
+            .sum()
+    }
+
+    /// Calculates the mean energy per spin at the given temperature.
+    pub fn energy(&self, t: f64) -> f64 {
+        self.canonical(t, |e| e) / self.sites as f64
+    }
+
+    /// Calculates the specific heat per spin at the given temperature. Follows the convention of
+    /// `Lattice::specific_heat_per_spin` which uses the fluctuations of the energy per spin.
+    pub fn specific_heat(&self, t: f64) -> f64 {
+        let sites = self.sites as f64;
+        let mean = self.canonical(t, |e| e / sites);
+        let sqr_mean = self.canonical(t, |e| (e / sites).powi(2));
+        (sqr_mean - mean.powi(2)) / t.powi(2)
+    }
+
+    /// Calculates the free energy per spin at the given temperature.
+    pub fn free_energy(&self, t: f64) -> f64 {
+        -t * self.ln_partition(t) / self.sites as f64
+    }
+}
+
+/// Calculates ln(sum(exp(x))) without overflowing for large exponents.
+pub fn log_sum_exp<I>(values: I) -> f64
+where
+    I: Iterator<Item = f64> + Clone,
+{
+    let max = values.clone().fold(f64::NEG_INFINITY, f64::max);
+    if !max.is_finite() {
+        return max;
+    }
+    max + values.map(|x| f64::exp(x - max)).sum::<f64>().ln()
+}
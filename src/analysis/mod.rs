@@ -1,8 +1,11 @@
 mod autocorrelation;
 mod bootstrap;
+mod density;
+pub mod reweighting;
 
 pub use autocorrelation::autocorrelation;
 pub use bootstrap::bootstrap;
+pub use density::DensityOfStates;
 
 /// Holds the mean, stddev, tau, mean_sqr, stddev_sqr and tau_sqr values.
 #[derive(Clone)]
@@ -0,0 +1,442 @@
+use crate::analysis::density::log_sum_exp;
+use crate::analysis::{autocorrelation, DensityOfStates, Observable};
+use crate::utils::{mean, stddev};
+use rayon::prelude::*;
+
+/// Reweights the observables with the given log weights of each measurement. The series are
+/// thermalized and blocked using tau, and the error of every weighted mean is estimated by
+/// drawing the given number of bootstrap resamples from the blocks. Returns the mean and
+/// stddev of every observable.
+pub fn reweight<const N: usize>(
+    rng: &mut fastrand::Rng,
+    ln_weights: &[f64],
+    observables: [&[f64]; N],
+    tau: f64,
+    resamples: usize,
+) -> [(f64, f64); N] {
+    // Shift log weights by their maximum to prevent overflows
+    let skip = usize::min(3 * tau.ceil() as usize, ln_weights.len());
+    let shift = ln_weights[skip..]
+        .iter()
+        .fold(f64::NEG_INFINITY, |a, b| f64::max(a, *b));
+
+    // Sum weights and weighted observables per block
+    let size = usize::max(tau.ceil() as usize, 1);
+    let blocks = (skip..ln_weights.len())
+        .step_by(size)
+        .map(|start| {
+            let mut sums = (0.0, [0.0; N]);
+            for i in start..usize::min(start + size, ln_weights.len()) {
+                let weight = f64::exp(ln_weights[i] - shift);
+                sums.0 += weight;
+                for (sum, observable) in sums.1.iter_mut().zip(observables) {
+                    *sum += weight * observable[i];
+                }
+            }
+            sums
+        })
+        .collect::<Vec<_>>();
+
+    // Bootstrap the weighted means by resampling blocks with repetition
+    let estimate = ratio(blocks.iter());
+    let resampled = (0..resamples)
+        .map(|_| ratio((0..blocks.len()).filter_map(|_| rng.choice(&blocks))))
+        .collect::<Vec<_>>();
+
+    std::array::from_fn(|k| {
+        let values = resampled.iter().map(|x| x[k]).collect::<Vec<_>>();
+        (estimate[k], stddev(&values, mean(&values)))
+    })
+}
+
+/// Divides the summed weighted observables by the summed weights over the picked blocks.
+fn ratio<'a, const N: usize>(picks: impl Iterator<Item = &'a (f64, [f64; N])>) -> [f64; N] {
+    let mut total = (0.0, [0.0; N]);
+    for (weight, sums) in picks {
+        total.0 += weight;
+        for (t, s) in total.1.iter_mut().zip(sums) {
+            *t += s;
+        }
+    }
+    total.1.map(|x| x / total.0)
+}
+
+/// Reweights the energy and magnetization per spin of a multicanonical simulation with the
+/// given density of states to the canonical ensemble at temperature t. Returns the energy and
+/// magnetization observables.
+pub fn multicanonical(
+    rng: &mut fastrand::Rng,
+    density: &DensityOfStates,
+    energies: &[f64],
+    magnets: &[f64],
+    t: f64,
+    resamples: usize,
+) -> (Observable, Observable) {
+    // Canonical weight exp(-E/T) divided by the multicanonical weight 1/g(E). Measurements
+    // taken before the lattice entered the range of the density carry no weight.
+    let sites = density.sites as f64;
+    let ln_weights = energies
+        .iter()
+        .map(|e| {
+            density
+                .bin(e * sites)
+                .map_or(f64::NEG_INFINITY, |bin| density.ln_g[bin] - e * sites / t)
+        })
+        .collect::<Vec<_>>();
+
+    canonical(rng, &ln_weights, energies, magnets, resamples)
+}
+
+/// Reweights the energy and magnetization per spin with the given log weights and determines
+/// the autocorrelation times of the underlying series. Returns the energy and magnetization
+/// observables.
+pub fn canonical(
+    rng: &mut fastrand::Rng,
+    ln_weights: &[f64],
+    energies: &[f64],
+    magnets: &[f64],
+    resamples: usize,
+) -> (Observable, Observable) {
+    let energies_sqr = energies.iter().map(|x| x.powi(2)).collect::<Vec<_>>();
+    let magnets_sqr = magnets.iter().map(|x| x.powi(2)).collect::<Vec<_>>();
+
+    // Autocorrelation times of the sampled series
+    let (tau_e, _) = autocorrelation(energies);
+    let (tau_e_sqr, _) = autocorrelation(&energies_sqr);
+    let (tau_m, _) = autocorrelation(magnets);
+    let (tau_m_sqr, _) = autocorrelation(&magnets_sqr);
+
+    // Block with the largest tau so the weights are blocked equally for every observable
+    let tau = [tau_e, tau_e_sqr, tau_m, tau_m_sqr]
+        .into_iter()
+        .fold(0.0, f64::max);
+    let [e, e_sqr, m, m_sqr] = reweight(
+        rng,
+        ln_weights,
+        [energies, &energies_sqr, magnets, &magnets_sqr],
+        tau,
+        resamples,
+    );
+
+    (
+        Observable::new(e.0, e.1, tau_e, e_sqr.0, e_sqr.1, tau_e_sqr),
+        Observable::new(m.0, m.1, tau_m, m_sqr.0, m_sqr.1, tau_m_sqr),
+    )
+}
+
+/// The maximum number of samples which are kept per histogram after thinning.
+const MAX_SAMPLES: usize = 10_000;
+
+/// The number of blocks each histogram is divided into for the bootstrap analysis.
+const BLOCKS: usize = 20;
+
+/// The number of energy bins used to solve the WHAM equations.
+const WHAM_BINS: usize = 1024;
+
+/// The largest change of a free energy at which the WHAM iteration is considered converged.
+const WHAM_TOLERANCE: f64 = 1e-10;
+
+/// The maximum number of WHAM iterations.
+const WHAM_ITERATIONS: usize = 100_000;
+
+/// Holds the thinned energy and magnetization per spin series measured at a temperature.
+pub struct Histogram {
+    pub temperature: f64,
+    pub sites: usize,
+    pub energies: Vec<f64>,
+    pub magnets: Vec<f64>,
+}
+
+impl Histogram {
+    /// Thermalizes the series by skipping the first 3*ceil(tau) elements of the energy series
+    /// and thins them, so that the remaining samples are roughly independent and at most
+    /// MAX_SAMPLES samples are kept.
+    pub fn new(temperature: f64, sites: usize, energies: &[f64], magnets: &[f64]) -> Self {
+        let (tau, _) = autocorrelation(energies);
+        let skip = usize::min(3 * tau.ceil() as usize, energies.len());
+        let stride = usize::max(tau.ceil() as usize, (energies.len() - skip) / MAX_SAMPLES).max(1);
+        Self {
+            temperature,
+            sites,
+            energies: energies[skip..].iter().step_by(stride).copied().collect(),
+            magnets: magnets[skip..].iter().step_by(stride).copied().collect(),
+        }
+    }
+}
+
+/// Holds the reweighted specific heat and magnetic susceptibility with their stddev.
+pub struct Curve {
+    pub temperature: f64,
+    pub cv: (f64, f64),
+    pub xs: (f64, f64),
+}
+
+/// Holds the interpolated position and height of a peak with their stddev.
+pub struct Peak {
+    pub temperature: (f64, f64),
+    pub value: (f64, f64),
+}
+
+/// Holds the reweighted curves and the peaks of the specific heat and the susceptibility.
+pub struct Reweighted {
+    pub curves: Vec<Curve>,
+    pub cv_peak: Peak,
+    pub xs_peak: Peak,
+}
+
+/// Reweights every histogram to the given temperatures which lie closest to its own
+/// temperature using the Ferrenberg Swendsen single histogram method.
+pub fn single_histogram(
+    rng: &mut fastrand::Rng,
+    histograms: &[Histogram],
+    temperatures: &[f64],
+    resamples: usize,
+) -> Reweighted {
+    curves(rng, histograms, temperatures, resamples, |t, k, n| {
+        let closest = histograms
+            .iter()
+            .map(|h| (h.temperature - t).abs())
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(k, _)| k);
+
+        // Only the closest histogram contributes at this temperature
+        let histogram = &histograms[k];
+        match closest == Some(k) {
+            true => -(t.recip() - histogram.temperature.recip()) * total(histogram, n),
+            false => f64::NEG_INFINITY,
+        }
+    })
+}
+
+/// Combines all histograms using the weighted histogram analysis method (WHAM) and reweights
+/// them to the given temperatures.
+pub fn multi_histogram(
+    rng: &mut fastrand::Rng,
+    histograms: &[Histogram],
+    temperatures: &[f64],
+    resamples: usize,
+) -> Reweighted {
+    // Log of the WHAM denominator sum_m N_m exp(f_m - beta_m E) for every sample
+    let free = wham(histograms);
+    let denominators = histograms
+        .iter()
+        .map(|h| {
+            (0..h.energies.len())
+                .map(|n| denominator(histograms, &free, total(h, n)))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    curves(rng, histograms, temperatures, resamples, |t, k, n| {
+        -total(&histograms[k], n) / t - denominators[k][n]
+    })
+}
+
+/// Solves the WHAM equations for the dimensionless free energies f_k = -ln Z_k of the histograms
+/// by self consistent iteration over a binned energy histogram. The free energy of the first
+/// histogram is fixed to zero.
+pub fn wham(histograms: &[Histogram]) -> Vec<f64> {
+    // Determine energy range of all samples
+    let energies = || {
+        histograms
+            .iter()
+            .flat_map(|h| (0..h.energies.len()).map(|n| total(h, n)))
+    };
+    let min = energies().fold(f64::INFINITY, f64::min);
+    let max = energies().fold(f64::NEG_INFINITY, f64::max);
+    let width = f64::max(max - min, f64::EPSILON) / WHAM_BINS as f64;
+
+    // Count samples of all histograms per energy bin
+    let mut counts = vec![0_usize; WHAM_BINS];
+    for energy in energies() {
+        counts[usize::min(((energy - min) / width) as usize, WHAM_BINS - 1)] += 1;
+    }
+    let bins = counts
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c > 0)
+        .map(|(b, c)| (min + (b as f64 + 0.5) * width, (*c as f64).ln()))
+        .collect::<Vec<_>>();
+
+    let mut free = vec![0.0; histograms.len()];
+    for _ in 0..WHAM_ITERATIONS {
+        // Estimate the log density of states of every bin from the current free energies
+        let ln_g = bins
+            .iter()
+            .map(|(energy, ln_count)| ln_count - denominator(histograms, &free, *energy))
+            .collect::<Vec<_>>();
+
+        // Update free energies from the density of states
+        let mut next = histograms
+            .iter()
+            .map(|h| {
+                let beta = h.temperature.recip();
+                -log_sum_exp(bins.iter().zip(&ln_g).map(|((e, _), g)| g - beta * e))
+            })
+            .collect::<Vec<_>>();
+        let offset = next[0];
+        next.iter_mut().for_each(|f| *f -= offset);
+
+        // Stop once the free energies converged
+        let change = free
+            .iter()
+            .zip(&next)
+            .fold(0.0, |a, (f, n)| f64::max(a, (f - n).abs()));
+        free = next;
+        if change < WHAM_TOLERANCE {
+            break;
+        }
+    }
+    free
+}
+
+/// Calculates the log of the WHAM denominator sum_m N_m exp(f_m - beta_m E) for a total energy.
+fn denominator(histograms: &[Histogram], free: &[f64], energy: f64) -> f64 {
+    log_sum_exp(
+        histograms
+            .iter()
+            .zip(free)
+            .map(|(h, f)| (h.energies.len() as f64).ln() + f - h.temperature.recip() * energy),
+    )
+}
+
+/// Returns the total energy of the n-th sample of the histogram.
+fn total(histogram: &Histogram, n: usize) -> f64 {
+    histogram.energies[n] * histogram.sites as f64
+}
+
+/// Calculates the specific heat and susceptibility at the given temperatures from the samples
+/// weighted with the given log weight of temperature t, histogram k and sample n. The errors are
+/// estimated by resampling the blocks of each histogram with repetition.
+fn curves<F>(
+    rng: &mut fastrand::Rng,
+    histograms: &[Histogram],
+    temperatures: &[f64],
+    resamples: usize,
+    ln_weight: F,
+) -> Reweighted
+where
+    F: Fn(f64, usize, usize) -> f64 + Sync,
+{
+    // Divide every histogram into blocks and remember which blocks belong to which histogram
+    let (mut blocks, mut strata) = (Vec::new(), Vec::new());
+    for (k, h) in histograms.iter().enumerate() {
+        let size = h.energies.len().div_ceil(BLOCKS).max(1);
+        let start = blocks.len();
+        for first in (0..h.energies.len()).step_by(size) {
+            blocks.push((k, first..usize::min(first + size, h.energies.len())));
+        }
+        strata.push(start..blocks.len());
+    }
+
+    // Sum weights and weighted e, e^2, m and m^2 per block for every temperature
+    let sums = temperatures
+        .par_iter()
+        .map(|&t| {
+            let shift = blocks
+                .iter()
+                .flat_map(|(k, r)| r.clone().map(|n| ln_weight(t, *k, n)))
+                .fold(f64::NEG_INFINITY, f64::max);
+            blocks
+                .iter()
+                .map(|(k, r)| {
+                    let mut sum = [0.0; 5];
+                    for n in r.clone() {
+                        let (e, m) = (histograms[*k].energies[n], histograms[*k].magnets[n]);
+                        let w = f64::exp(ln_weight(t, *k, n) - shift);
+                        sum[0] += w;
+                        sum[1] += w * e;
+                        sum[2] += w * e * e;
+                        sum[3] += w * m;
+                        sum[4] += w * m * m;
+                    }
+                    sum
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // Calculates Cv and Xs at every temperature from the picked blocks
+    let observe = |picked: &[usize]| -> (Vec<f64>, Vec<f64>) {
+        temperatures
+            .iter()
+            .zip(&sums)
+            .map(|(t, sums)| {
+                let mut total = [0.0; 5];
+                for i in picked {
+                    total.iter_mut().zip(&sums[*i]).for_each(|(a, b)| *a += b);
+                }
+                let [_, e, e_sqr, m, m_sqr] = total.map(|x| x / total[0]);
+                ((e_sqr - e * e) / t.powi(2), (m_sqr - m * m) / t)
+            })
+            .unzip()
+    };
+
+    // Full estimate and resamples which keep the number of blocks per histogram
+    let (cv, xs) = observe(&(0..blocks.len()).collect::<Vec<_>>());
+    let (cv_resampled, xs_resampled): (Vec<_>, Vec<_>) = (0..resamples)
+        .map(|_| {
+            let picked = strata
+                .iter()
+                .flat_map(|s| s.clone().map(|_| rng.usize(s.clone())).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            observe(&picked)
+        })
+        .unzip();
+
+    // Stddev of the curves at every temperature
+    let spread = |values: Vec<f64>| stddev(&values, mean(&values));
+    let curves = temperatures
+        .iter()
+        .enumerate()
+        .map(|(i, &temperature)| Curve {
+            temperature,
+            cv: (cv[i], spread(cv_resampled.iter().map(|r| r[i]).collect())),
+            xs: (xs[i], spread(xs_resampled.iter().map(|r| r[i]).collect())),
+        })
+        .collect();
+
+    // Interpolated peaks of the full estimate and the stddev over the resamples
+    let peaks = |values: &[f64], resampled: &[Vec<f64>]| {
+        let (temperature, value) = peak(temperatures, values);
+        let (ts, vs) = resampled
+            .iter()
+            .map(|r| peak(temperatures, r))
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        Peak {
+            temperature: (temperature, spread(ts)),
+            value: (value, spread(vs)),
+        }
+    };
+
+    Reweighted {
+        curves,
+        cv_peak: peaks(&cv, &cv_resampled),
+        xs_peak: peaks(&xs, &xs_resampled),
+    }
+}
+
+/// Finds the maximum of the values on the equally spaced temperatures and refines its position
+/// and height by fitting a parabola through the maximum and its neighbours.
+fn peak(temperatures: &[f64], values: &[f64]) -> (f64, f64) {
+    let Some((i, &max)) = values.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)) else {
+        return (f64::NAN, f64::NAN);
+    };
+    if i == 0 || i + 1 == values.len() {
+        return (temperatures[i], max);
+    }
+
+    let (left, right) = (values[i - 1], values[i + 1]);
+    let curvature = left - 2.0 * max + right;
+    if curvature >= 0.0 {
+        return (temperatures[i], max);
+    }
+
+    let offset = 0.5 * (left - right) / curvature;
+    let stride = temperatures[i + 1] - temperatures[i];
+    (
+        temperatures[i] + offset * stride,
+        max - 0.25 * (left - right) * offset,
+    )
+}
@@ -1,24 +1,251 @@
-use clap::Parser;
+use crate::algorithm::quench::Schedule;
+use crate::algorithm::{Method, Parameters};
+use crate::lattice::{Boundary, Geometry, Precision};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, ValueEnum};
 
 #[derive(Parser)]
 #[command(
     version = "1.0",
-    about = "Simulates the XY model using a metropolis hastings algorithm. Results are written to the output.sqlite database."
+    about = "Simulates the XY model using Monte Carlo algorithms. Results are written to the output.sqlite database."
 )]
 pub struct Arguments {
     /// Optionally provide a run id for which the results should be gathered.
     #[arg(short = 'r', long = "run_id")]
     pub run_id: Option<i32>,
 
+    /// The parameters of the Monte Carlo algorithm.
+    #[command(flatten)]
+    pub parameters: Parameters,
+
     /// Enables the recording of the development of vortices on the lattice
     #[arg(short = 'v', long = "vortices")]
     pub vortices: Option<usize>,
 
+    /// Enables the recording of the real time dynamics of the 2D lattice after a quench from
+    /// infinite temperature. Use the Langevin or Glauber algorithm for physical time units.
+    #[arg(short = 'd', long = "dynamics")]
+    pub dynamics: Option<usize>,
+
+    /// Enables the recording of the vortex density of the 2D lattice during quenches through
+    /// the temperatures of the schedule for every quench rate.
+    #[arg(short = 'q', long = "quench")]
+    pub quench: Option<usize>,
+
+    /// The temperature schedule T(t) of the quenches and the annealing.
+    #[arg(long = "schedule", value_enum, default_value_t = Schedule::Linear)]
+    pub schedule: Schedule,
+
+    /// The temperatures the quenches and the annealing pass through, starting at the first and
+    /// ending at the last.
+    #[arg(long = "knots", num_args = 2.., default_values_t = [2.0, 0.05])]
+    pub knots: Vec<f64>,
+
+    /// The quench rates, that is the inverse quench times.
+    #[arg(long = "rates", num_args = 1.., default_values_t = [1e-2, 1e-3, 1e-4])]
+    pub rates: Vec<f64>,
+
+    /// The number of independent quenches per rate.
+    #[arg(long = "quenches", default_value_t = 64)]
+    pub quenches: usize,
+
+    /// Enables the search for the ground state of the 2D lattice using simulated annealing
+    /// along the temperature schedule.
+    #[arg(long = "annealing")]
+    pub annealing: Option<usize>,
+
+    /// The number of sweeps the annealing takes to cool the lattice.
+    #[arg(long = "annealing_sweeps", default_value_t = 100_000)]
+    pub annealing_sweeps: usize,
+
+    /// The geometry of the 2D lattices of the canonical simulations.
+    #[arg(long = "geometry", value_enum, default_value_t = Geometry::Square)]
+    pub geometry: Geometry,
+
+    /// Simulates the q-state clock model with the given number of states instead of the XY model
+    /// on the 2D lattices. Supported are 2, 3, 4, 5, 6, 8, 12 and 16 states, which are sampled
+    /// by the Metropolis or checkerboard method proposing one of the other states, or by the
+    /// vectorized method proposing uniform angles rounded to the nearest state.
+    #[arg(
+        long = "states",
+        conflicts_with_all = [
+            "one",
+            "three",
+            "geometry",
+            "precision",
+            "overrelaxation",
+            "acceptance"
+        ],
+        value_parser = parse_states
+    )]
+    pub states: Option<usize>,
+
+    /// The precision of the spins of the canonical simulations, the benchmark and the validation.
+    /// Discrete angles cannot be combined with methods which reflect, integrate or continuously
+    /// draw angles.
+    #[arg(long = "precision", value_enum, default_value_t = Precision::Double)]
+    pub precision: Precision,
+
+    /// Compares the observables of the 2D lattice of the given size in the selected and in double
+    /// precision at several temperatures and exits.
+    #[arg(long = "validate")]
+    pub validate: Option<usize>,
+
+    /// Measures the throughput of the scalar and the vectorized Metropolis sweep on the 2D
+    /// lattice of the given size in site updates per second and exits.
+    #[arg(long = "benchmark")]
+    pub benchmark: Option<usize>,
+
+    /// Enables the estimation of the density of states of the 2D lattice using Wang Landau.
+    #[arg(short = 'w', long = "wang_landau")]
+    pub wang_landau: Option<usize>,
+
+    /// Replaces the canonical simulations of the Wang Landau lattice size by a single
+    /// multicanonical simulation which is reweighted to the canonical temperatures.
+    #[arg(short = 'm', long = "multicanonical", requires = "wang_landau")]
+    pub multicanonical: bool,
+
+    /// The temperatures at which the density of states and the multicanonical simulation are
+    /// evaluated. Defaults to the initial temperature range of the canonical simulations.
+    #[arg(long = "temperatures", num_args = 0..)]
+    pub temperatures: Vec<f64>,
+
+    /// The boundary conditions of the canonically simulated lattices along the x, y and z
+    /// direction, each being free, periodic, antiperiodic or a twist angle. Directions without a
+    /// given boundary condition repeat the last one.
+    #[arg(
+        long = "boundaries",
+        num_args = 1..=3,
+        value_parser = parse_boundary,
+        default_value = "periodic"
+    )]
+    pub boundaries: Vec<Boundary>,
+
     /// The lengths of the 1D lattice sides which will be simulated.
     #[arg(short = 'o', long = "one", num_args = 0..)]
     pub one: Vec<usize>,
 
-    /// The lengths of the 2D lattice sides which will be simulated.
-    #[arg(short = 't', long = "two", num_args = 0..)]
-    pub two: Vec<usize>,
+    /// The extents of the 2D lattices which will be simulated, either a single length for a
+    /// square lattice or length and height as in 256x4 for a rectangle or strip.
+    #[arg(short = 't', long = "two", num_args = 0.., value_parser = parse_extent)]
+    pub two: Vec<(usize, usize)>,
+
+    /// The extents of the 3D lattices which will be simulated, either a single length for a
+    /// cube or the side length of the square layers and the height as in 64x4 for a film.
+    #[arg(long = "three", num_args = 0.., value_parser = parse_extent)]
+    pub three: Vec<(usize, usize)>,
+}
+
+impl Arguments {
+    /// Checks the combinations of options which clap cannot express, since they depend on the
+    /// selected values. Exits with a usage error for the first invalid combination.
+    pub fn validate(self) -> Self {
+        if let Some(message) = self.conflict() {
+            Self::command()
+                .error(ErrorKind::ArgumentConflict, message)
+                .exit();
+        }
+        self
+    }
+
+    /// Returns the message of the first combination of options which cannot be used together.
+    fn conflict(&self) -> Option<String> {
+        let method = format!("--algorithm {}", name(self.parameters.method));
+        let precision = format!("--precision {}", name(self.precision));
+        let discrete = matches!(
+            self.precision,
+            Precision::Discrete1024 | Precision::Discrete65536
+        );
+        let cluster = matches!(self.parameters.method, Method::Wolff | Method::SwendsenWang);
+        let twisted = self
+            .boundaries
+            .iter()
+            .any(|b| matches!(b, Boundary::Twisted(_)));
+        let symmetric = matches!(
+            self.parameters.method,
+            Method::Metropolis | Method::Checkerboard | Method::Vectorized
+        );
+        let continuous = matches!(
+            self.parameters.method,
+            Method::Wolff
+                | Method::SwendsenWang
+                | Method::HeatBath
+                | Method::Hybrid
+                | Method::Langevin
+        );
+
+        // Discrete angles cannot represent reflected, integrated or continuously drawn angles,
+        // the clock states are rounded, which only keeps uniform proposals symmetric, and
+        // clusters cannot be reflected across a twisted boundary
+        let conflicts = [
+            (discrete && continuous, precision.as_str(), method.as_str()),
+            (
+                discrete && self.parameters.overrelaxation > 0,
+                precision.as_str(),
+                "--overrelaxation",
+            ),
+            (
+                discrete && self.annealing.is_some(),
+                precision.as_str(),
+                "--annealing",
+            ),
+            (
+                self.states.is_some() && !symmetric,
+                "--states",
+                method.as_str(),
+            ),
+            (
+                twisted && cluster,
+                "a twisted --boundaries",
+                method.as_str(),
+            ),
+        ];
+
+        for (conflict, first, second) in conflicts {
+            if conflict {
+                return Some(format!("{} cannot be used with {}", first, second));
+            }
+        }
+        None
+    }
+}
+
+/// Returns the name of the value as given on the command line.
+fn name<T: ValueEnum>(value: T) -> String {
+    value
+        .to_possible_value()
+        .map_or_else(String::new, |v| v.get_name().to_owned())
+}
+
+/// Parses the extent of a lattice given either as a single length or as length and height
+/// separated by an x.
+fn parse_extent(value: &str) -> Result<(usize, usize), String> {
+    let (length, height) = value.split_once('x').unwrap_or((value, value));
+    let length = length.parse::<usize>().map_err(|e| e.to_string())?;
+    let height = height.parse::<usize>().map_err(|e| e.to_string())?;
+    Ok((length, height))
+}
+
+/// Parses the number of states of the clock model, which must be one of the supported values.
+fn parse_states(value: &str) -> Result<usize, String> {
+    let states = value.parse::<usize>().map_err(|e| e.to_string())?;
+    match states {
+        2 | 3 | 4 | 5 | 6 | 8 | 12 | 16 => Ok(states),
+        _ => Err(String::from(
+            "supported are 2, 3, 4, 5, 6, 8, 12 and 16 states",
+        )),
+    }
+}
+
+/// Parses a boundary condition, which is either free, periodic, antiperiodic or a twist angle.
+fn parse_boundary(value: &str) -> Result<Boundary, String> {
+    match value {
+        "free" => Ok(Boundary::Free),
+        "periodic" => Ok(Boundary::Periodic),
+        "antiperiodic" => Ok(Boundary::Antiperiodic),
+        _ => value
+            .parse::<f64>()
+            .map(Boundary::Twisted)
+            .map_err(|_| String::from("expected free, periodic, antiperiodic or a twist angle")),
+    }
 }
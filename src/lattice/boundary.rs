@@ -0,0 +1,101 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+/// The boundary condition of a lattice along one direction. Bonds crossing a twisted boundary
+/// couple the spins as cos(a - b - phi), which is equivalent to continuing the lattice with all
+/// spins rotated by the twist angle phi. The cluster algorithms reflect both spins of a bond
+/// about the same axis, so they only sample free, periodic and antiperiodic boundaries exactly
+/// and cannot be combined with twists.
+#[derive(Clone, Copy)]
+pub enum Boundary {
+    /// Bonds crossing the boundary are cut, leaving open surfaces.
+    Free,
+    /// Bonds crossing the boundary couple like all other bonds.
+    Periodic,
+    /// Bonds crossing the boundary are antiferromagnetic, which is a twist by π.
+    Antiperiodic,
+    /// Bonds crossing the boundary are twisted by the given angle.
+    Twisted(f64),
+}
+
+impl Boundary {
+    /// Returns the twist angle picked up by a bond crossing the boundary in forward direction or
+    /// none if such bonds are cut.
+    pub fn twist(self) -> Option<f64> {
+        match self {
+            Boundary::Free => None,
+            Boundary::Periodic => Some(0.0),
+            Boundary::Antiperiodic => Some(PI),
+            Boundary::Twisted(angle) => Some(angle),
+        }
+    }
+}
+
+/// Formats the boundary condition as given on the command line.
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Boundary::Free => write!(f, "free"),
+            Boundary::Periodic => write!(f, "periodic"),
+            Boundary::Antiperiodic => write!(f, "antiperiodic"),
+            Boundary::Twisted(angle) => write!(f, "{}", angle),
+        }
+    }
+}
+
+/// The boundary conditions of a lattice along its x, y and z direction.
+#[derive(Clone, Copy)]
+pub struct Boundaries([Boundary; 3]);
+
+impl Boundaries {
+    /// Creates the boundary conditions from the given ones in x, y and z order. Directions
+    /// without a given boundary condition repeat the last one.
+    pub fn new(boundaries: &[Boundary]) -> Self {
+        let last = boundaries.last().copied().unwrap_or(Boundary::Periodic);
+        Self(std::array::from_fn(|axis| {
+            boundaries.get(axis).copied().unwrap_or(last)
+        }))
+    }
+
+    /// Returns the twist angles along the x, y and z direction, none for free boundaries.
+    pub fn twists(&self) -> [Option<f64>; 3] {
+        self.0.map(Boundary::twist)
+    }
+
+    /// Moves the spin at index i by one step forward or backward along the given axis, where the
+    /// coordinate along the axis has the given stride and extent. Returns the index of the
+    /// neighbour together with the phase the bond picks up or none if the step crosses a free
+    /// boundary. The phase of the backward bond is the negated phase of the forward bond.
+    pub fn shift(
+        &self,
+        i: usize,
+        axis: usize,
+        stride: usize,
+        extent: usize,
+        forward: bool,
+    ) -> Option<(usize, f64)> {
+        let coordinate = (i / stride) % extent;
+        let (next, phase) = match (forward, coordinate) {
+            (true, c) if c == extent - 1 => (0, self.0[axis].twist()?),
+            (true, c) => (c + 1, 0.0),
+            (false, 0) => (extent - 1, -self.0[axis].twist()?),
+            (false, c) => (c - 1, 0.0),
+        };
+        Some((i - coordinate * stride + next * stride, phase))
+    }
+}
+
+impl Default for Boundaries {
+    fn default() -> Self {
+        Self([Boundary::Periodic; 3])
+    }
+}
+
+/// Formats the boundary conditions of all three directions separated by spaces, which labels the
+/// allocations simulated with them.
+impl fmt::Display for Boundaries {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [x, y, z] = self.0;
+        write!(f, "{} {} {}", x, y, z)
+    }
+}
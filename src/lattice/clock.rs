@@ -0,0 +1,182 @@
+use crate::constants::MAX_ANGLE;
+use crate::lattice::spin::cosines;
+use crate::lattice::{helicity_sum, Boundaries, Lattice};
+use std::f64::consts::PI;
+use std::ops::RangeInclusive;
+
+/// The square lattice of the q-state clock model, where every spin takes one of the Q angles
+/// 2πk/Q. The state k is stored instead of the angle and all cosines and sines are looked up
+/// from tables. Angles passed to the lattice are rounded to the nearest state, so only the
+/// algorithms with symmetric proposals between states sample the clock model correctly. These are
+/// Metropolis and checkerboard, which propose one of the other states, and vectorized, whose
+/// uniform angles are rounded to a uniformly drawn state before the energy difference is taken.
+pub struct ClockLattice<const Q: usize> {
+    beta: f64,
+    length: usize,
+    height: usize,
+    boundaries: Boundaries,
+    states: Box<[usize]>,
+}
+
+impl<const Q: usize> ClockLattice<Q> {
+    /// The cosine of the angles of all states.
+    const COS: &'static [f64; Q] = &cosines::<Q>(0.0);
+
+    /// The sine of the angles of all states.
+    const SIN: &'static [f64; Q] = &cosines::<Q>(PI / 2.0);
+
+    /// Returns the state whose angle lies closest to the given angle.
+    fn state(angle: f64) -> usize {
+        (angle.rem_euclid(MAX_ANGLE) * Q as f64 / MAX_ANGLE).round() as usize % Q
+    }
+
+    /// Looks up the cosine of the angle between the states k and l. Bonds across a twisted
+    /// boundary are evaluated directly, since the twist is no multiple of the state spacing.
+    fn bond(k: usize, l: usize, phase: f64) -> f64 {
+        if phase == 0.0 {
+            Self::COS[(k + Q - l) % Q]
+        } else {
+            f64::cos(MAX_ANGLE * (k as f64 - l as f64) / Q as f64 - phase)
+        }
+    }
+
+    /// Returns the index of the neighbour of the spin at index i along the given axis, which is
+    /// 0 for the x and 1 for the y direction, and the phase of the bond. Returns none if the
+    /// bond crosses a free boundary.
+    fn shift(&self, i: usize, axis: usize, forward: bool) -> Option<(usize, f64)> {
+        let (stride, extent) = [(1, self.length), (self.length, self.height)][axis];
+        self.boundaries.shift(i, axis, stride, extent, forward)
+    }
+}
+
+impl<const Q: usize> Lattice for ClockLattice<Q> {
+    const DIM: usize = 2;
+
+    // Ising transition at T = 2.27 for two states, Potts transition at T = 1.49 for three
+    // states, two decoupled Ising transitions at T = 1.13 for four states and BKT transitions
+    // which approach the one of the XY model for more states
+    const CRITICAL_WINDOW: Option<RangeInclusive<f64>> = Some(match Q {
+        2 => 2.0..=2.6,
+        3 => 1.3..=1.7,
+        4 => 0.95..=1.3,
+        _ => 0.60..=1.2,
+    });
+
+    fn new(length: usize, height: usize, boundaries: Boundaries, beta: f64) -> Self {
+        assert!(Q >= 2);
+        Self {
+            beta,
+            length,
+            height,
+            boundaries,
+            states: vec![0; length * height].into_boxed_slice(),
+        }
+    }
+
+    fn set_beta(&mut self, beta: f64) {
+        self.beta = beta;
+    }
+
+    fn beta(&self) -> f64 {
+        self.beta
+    }
+
+    fn temperature(&self) -> f64 {
+        self.beta.recip()
+    }
+
+    fn sites(&self) -> usize {
+        self.states.len()
+    }
+
+    fn angle(&self, i: usize) -> f64 {
+        MAX_ANGLE * self.states[i] as f64 / Q as f64
+    }
+
+    fn round_angle(&self, angle: f64) -> f64 {
+        MAX_ANGLE * Self::state(angle) as f64 / Q as f64
+    }
+
+    /// Proposes one of the other Q - 1 states uniformly, regardless of the proposal width.
+    fn propose(&self, rng: &mut fastrand::Rng, i: usize, _width: f64) -> f64 {
+        let state = (self.states[i] + 1 + rng.usize(..Q - 1)) % Q;
+        MAX_ANGLE * state as f64 / Q as f64
+    }
+
+    fn update_angle(&mut self, i: usize, angle: f64) {
+        self.states[i] = Self::state(angle);
+    }
+
+    fn energy(&self) -> f64 {
+        let mut result = 0.0;
+        for i in 0..self.sites() {
+            for (j, phase) in [0, 1]
+                .into_iter()
+                .filter_map(|axis| self.shift(i, axis, true))
+            {
+                result += Self::bond(self.states[i], self.states[j], phase);
+            }
+        }
+        -result
+    }
+
+    fn neighbours(&self, i: usize) -> impl Iterator<Item = (usize, f64)> {
+        [
+            self.shift(i, 0, true),
+            self.shift(i, 0, false),
+            self.shift(i, 1, true),
+            self.shift(i, 1, false),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    fn local_field(&self, i: usize) -> (f64, f64) {
+        self.neighbours(i)
+            .fold((0.0, 0.0), |(cos, sin), (j, phase)| {
+                let (s, c) = match phase == 0.0 {
+                    true => (Self::SIN[self.states[j]], Self::COS[self.states[j]]),
+                    false => (self.angle(j) + phase).sin_cos(),
+                };
+                (cos + c, sin + s)
+            })
+    }
+
+    fn energy_diff(&self, i: usize, angle: f64) -> f64 {
+        let (old, new) = (self.states[i], Self::state(angle));
+        self.neighbours(i)
+            .map(|(j, phase)| {
+                Self::bond(old, self.states[j], phase) - Self::bond(new, self.states[j], phase)
+            })
+            .sum()
+    }
+
+    fn magnetization(&self) -> (f64, f64) {
+        self.states.iter().fold((0.0, 0.0), |(cos, sin), &k| {
+            (cos + Self::COS[k], sin + Self::SIN[k])
+        })
+    }
+
+    fn magnetization_diff(&self, i: usize, angle: f64) -> (f64, f64) {
+        let (old, new) = (self.states[i], Self::state(angle));
+        (
+            Self::COS[new] - Self::COS[old],
+            Self::SIN[new] - Self::SIN[old],
+        )
+    }
+
+    fn helicity(&self) -> (f64, f64) {
+        helicity_sum((0..self.sites()).filter_map(|i| {
+            self.shift(i, 0, true)
+                .map(|(j, phase)| (self.angle(i), self.angle(j) + phase, 1.0))
+        }))
+    }
+
+    fn acceptance(&self, diff_energy: f64) -> f64 {
+        f64::min(1.0, f64::exp(-self.beta * diff_energy))
+    }
+
+    fn serialize(&self) -> String {
+        serde_json::to_string(&self.states).unwrap()
+    }
+}
@@ -0,0 +1,142 @@
+use crate::lattice::{bond_diff, bond_sum, helicity_sum, Boundaries, Lattice, Spin};
+use std::ops::{Index, RangeInclusive};
+
+/// The honeycomb lattice built from length x height unit cells of two sites each. The site at
+/// index 2c + s belongs to the unit cell c and the sublattice s. Every spin has three
+/// neighbours, all of them on the other sublattice.
+pub struct HoneycombLattice<S = f64> {
+    beta: f64,
+    length: usize,
+    height: usize,
+    boundaries: Boundaries,
+    spins: Box<[S]>,
+}
+
+impl<S: Spin> HoneycombLattice<S> {
+    /// Returns the index of the spin on the other sublattice in the unit cell of the spin at
+    /// index i or in the neighbouring cell along the given axis, and the phase of the bond. The
+    /// first sublattice connects to lower cells, the second one to upper cells. Returns none if
+    /// the bond crosses a free boundary.
+    fn partner(&self, i: usize, axis: Option<usize>) -> Option<(usize, f64)> {
+        let (j, phase) = match axis {
+            None => (i, 0.0),
+            Some(axis) => {
+                let (stride, extent) = [(2, self.length), (2 * self.length, self.height)][axis];
+                self.boundaries
+                    .shift(i, axis, stride, extent, !i.is_multiple_of(2))?
+            }
+        };
+        Some((j ^ 1, phase))
+    }
+}
+
+impl<S: Spin> Lattice for HoneycombLattice<S> {
+    const DIM: usize = 2;
+
+    // BKT transition at T = 0.58
+    const CRITICAL_WINDOW: Option<RangeInclusive<f64>> = Some(0.45..=0.75);
+
+    // 3√3 / 4
+    const VOLUME_PER_SITE: f64 = 1.299038105676658;
+
+    const LANES: usize = S::LANES;
+
+    fn new(length: usize, height: usize, boundaries: Boundaries, beta: f64) -> Self {
+        Self {
+            beta,
+            length,
+            height,
+            boundaries,
+            spins: vec![S::from_angle(0.0); 2 * length * height].into_boxed_slice(),
+        }
+    }
+
+    fn set_beta(&mut self, beta: f64) {
+        self.beta = beta;
+    }
+
+    fn beta(&self) -> f64 {
+        self.beta
+    }
+
+    fn temperature(&self) -> f64 {
+        self.beta.recip()
+    }
+
+    fn sites(&self) -> usize {
+        self.spins.len()
+    }
+
+    fn angle(&self, i: usize) -> f64 {
+        self[i].angle()
+    }
+
+    fn round_angle(&self, angle: f64) -> f64 {
+        S::from_angle(angle).angle()
+    }
+
+    fn update_angle(&mut self, i: usize, angle: f64) {
+        self.spins[i] = S::from_angle(angle);
+    }
+
+    fn energy(&self) -> f64 {
+        // Every bond is counted once from its site on the first sublattice
+        let bonds = (0..self.sites()).step_by(2).flat_map(|i| {
+            self.neighbours(i)
+                .map(move |(j, phase)| (self[i], self[j].rotate(phase)))
+        });
+        -bond_sum(bonds)
+    }
+
+    fn neighbours(&self, i: usize) -> impl Iterator<Item = (usize, f64)> {
+        [
+            self.partner(i, None),
+            self.partner(i, Some(0)),
+            self.partner(i, Some(1)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    fn energy_diff(&self, i: usize, angle: f64) -> f64 {
+        let neighbours = self.neighbours(i).map(|(j, phase)| self[j].rotate(phase));
+        bond_diff(self[i], S::from_angle(angle), neighbours)
+    }
+
+    fn magnetization_diff(&self, i: usize, angle: f64) -> (f64, f64) {
+        S::magnetization_diff(self[i], S::from_angle(angle))
+    }
+
+    fn helicity(&self) -> (f64, f64) {
+        // Every bond is taken from its site on the first sublattice. The bonds within the cell
+        // and to the lower cell along x advance by √3 / 2 bond lengths in positive and negative
+        // x direction, the bonds to the lower cell along y are perpendicular to x
+        let projection = 0.8660254037844386;
+        let bonds = (0..self.sites()).step_by(2).flat_map(|i| {
+            [(None, projection), (Some(0), -projection), (Some(1), 0.0)]
+                .into_iter()
+                .filter_map(move |(axis, projection)| {
+                    self.partner(i, axis)
+                        .map(|(j, phase)| (self.angle(i), self.angle(j) + phase, projection))
+                })
+        });
+        helicity_sum(bonds)
+    }
+
+    fn acceptance(&self, diff_energy: f64) -> f64 {
+        f64::min(1.0, f64::exp(-self.beta * diff_energy))
+    }
+
+    fn serialize(&self) -> String {
+        let angles = self.spins.iter().map(|s| s.angle()).collect::<Vec<_>>();
+        serde_json::to_string(&angles).unwrap()
+    }
+}
+
+impl<S> Index<usize> for HoneycombLattice<S> {
+    type Output = S;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.spins[index]
+    }
+}
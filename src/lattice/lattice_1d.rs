@@ -1,20 +1,35 @@
-use crate::lattice::Lattice;
-use std::ops::Index;
-use wide::{f64x2, f64x4};
+use crate::lattice::{bond_sum, helicity_sum, Boundaries, Lattice, Spin};
+use std::ops::{Index, RangeInclusive};
 
-pub struct Lattice1D {
+pub struct Lattice1D<S = f64> {
     beta: f64,
-    spins: Box<[f64]>,
+    boundaries: Boundaries,
+    spins: Box<[S]>,
 }
 
-impl Lattice for Lattice1D {
+impl<S: Spin> Lattice1D<S> {
+    /// Returns the index of the right or left neighbour of the spin at index i and the phase of
+    /// the bond, or none if the bond crosses a free boundary.
+    fn shift(&self, i: usize, forward: bool) -> Option<(usize, f64)> {
+        self.boundaries.shift(i, 0, 1, self.sites(), forward)
+    }
+}
+
+impl<S: Spin> Lattice for Lattice1D<S> {
     const DIM: usize = 1;
 
-    fn new(length: usize, beta: f64) -> Self {
-        assert_eq!(length % 4, 0);
+    // The chain has no transition at a finite temperature, its susceptibility grows with the
+    // correlation length up to zero temperature, so there is no peak to zoom into
+    const CRITICAL_WINDOW: Option<RangeInclusive<f64>> = None;
+
+    const LANES: usize = S::LANES;
+
+    fn new(length: usize, height: usize, boundaries: Boundaries, beta: f64) -> Self {
+        assert_eq!(height, 1);
         Self {
             beta,
-            spins: vec![0.0; length].into_boxed_slice(),
+            boundaries,
+            spins: vec![S::from_angle(0.0); length].into_boxed_slice(),
         }
     }
 
@@ -22,6 +37,10 @@ impl Lattice for Lattice1D {
         self.beta = beta;
     }
 
+    fn beta(&self) -> f64 {
+        self.beta
+    }
+
     fn temperature(&self) -> f64 {
         self.beta.recip()
     }
@@ -30,43 +49,60 @@ impl Lattice for Lattice1D {
         self.spins.len()
     }
 
+    fn angle(&self, i: usize) -> f64 {
+        self[i].angle()
+    }
+
+    fn round_angle(&self, angle: f64) -> f64 {
+        S::from_angle(angle).angle()
+    }
+
     fn update_angle(&mut self, i: usize, angle: f64) {
-        self.spins[i] = angle;
+        self.spins[i] = S::from_angle(angle);
     }
 
     fn energy(&self) -> f64 {
-        let mut result = 0.0;
-        for i in (0..self.sites()).step_by(4) {
-            let old = f64x4::splat(self[i]);
-            let neighbours = f64x4::new([
-                self[(i + 1) % self.sites()],
-                self[(i + 2) % self.sites()],
-                self[(i + 3) % self.sites()],
-                self[(i + 4) % self.sites()],
-            ]);
-            result += (old - neighbours).cos().reduce_add();
-        }
-        -result
+        let bonds = (0..self.sites()).filter_map(|i| {
+            self.shift(i, true)
+                .map(|(j, phase)| (self[i], self[j].rotate(phase)))
+        });
+        -bond_sum(bonds)
     }
 
-    fn energy_diff(&self, i: usize, angle: f64) -> f64 {
-        let neighbours = f64x2::from([
-            self[(i + 1) % self.sites()],
-            self[(i + self.sites() - 1) % self.sites()],
-        ]);
-
-        let old = f64x2::splat(self[i]);
-        let before = (old - neighbours).cos().reduce_add();
+    fn neighbours(&self, i: usize) -> impl Iterator<Item = (usize, f64)> {
+        [self.shift(i, true), self.shift(i, false)]
+            .into_iter()
+            .flatten()
+    }
 
-        let new = f64x2::splat(angle);
-        let after = (new - neighbours).cos().reduce_add();
+    fn energy_diff(&self, i: usize, angle: f64) -> f64 {
+        let [right, left] = [true, false].map(|forward| self.shift(i, forward));
+
+        // Missing neighbours compare the spin with itself before and after the change
+        let (old, new) = (self[i], S::from_angle(angle));
+        let neighbour = |n: Option<(usize, f64)>, s: S| n.map_or(s, |(j, p)| self[j].rotate(p));
+        let (before, after) = S::bond_pairs(
+            [old, old, new, new],
+            [
+                neighbour(right, old),
+                neighbour(left, old),
+                neighbour(right, new),
+                neighbour(left, new),
+            ],
+        );
 
         before - after
     }
 
     fn magnetization_diff(&self, i: usize, angle: f64) -> (f64, f64) {
-        let (sin, cos) = f64x2::from([angle, std::f64::consts::PI + self[i]]).sin_cos();
-        (cos.reduce_add(), sin.reduce_add())
+        S::magnetization_diff(self[i], S::from_angle(angle))
+    }
+
+    fn helicity(&self) -> (f64, f64) {
+        helicity_sum((0..self.sites()).filter_map(|i| {
+            self.shift(i, true)
+                .map(|(j, phase)| (self.angle(i), self.angle(j) + phase, 1.0))
+        }))
     }
 
     fn acceptance(&self, diff_energy: f64) -> f64 {
@@ -74,14 +110,39 @@ impl Lattice for Lattice1D {
     }
 
     fn serialize(&self) -> String {
-        serde_json::to_string(&self.spins).unwrap()
+        let angles = self.spins.iter().map(|s| s.angle()).collect::<Vec<_>>();
+        serde_json::to_string(&angles).unwrap()
     }
 }
 
-impl Index<usize> for Lattice1D {
-    type Output = f64;
+impl<S> Index<usize> for Lattice1D<S> {
+    type Output = S;
 
     fn index(&self, index: usize) -> &Self::Output {
         &self.spins[index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::MAX_ANGLE;
+
+    /// The total energy must change by the local energy difference of every update, which fails
+    /// if the vectorized energy pairs sites other than the nearest neighbours.
+    #[test]
+    fn energy_changes_by_energy_diff() {
+        let mut rng = fastrand::Rng::with_seed(1);
+        let mut lattice = <Lattice1D>::new(16, 1, Boundaries::default(), 1.0);
+        for i in 0..lattice.sites() {
+            lattice.update_angle(i, rng.f64() * MAX_ANGLE);
+        }
+
+        for _ in 0..100 {
+            let (i, angle) = (rng.usize(..lattice.sites()), rng.f64() * MAX_ANGLE);
+            let (before, diff) = (lattice.energy(), lattice.energy_diff(i, angle));
+            lattice.update_angle(i, angle);
+            assert!((lattice.energy() - before - diff).abs() < 1e-9);
+        }
+    }
+}
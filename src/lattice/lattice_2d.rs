@@ -1,22 +1,73 @@
-use crate::lattice::Lattice;
-use std::ops::Index;
-use wide::{f64x2, f64x4};
+use crate::constants::MAX_ANGLE;
+use crate::lattice::{bond_diff, bond_sum, helicity_sum, Boundaries, Lattice, Spin};
+use std::f64::consts::PI;
+use std::ops::{Index, RangeInclusive};
 
-pub struct Lattice2D {
+pub struct Lattice2D<S = f64> {
     beta: f64,
     length: usize,
-    spins: Box<[f64]>,
+    height: usize,
+    boundaries: Boundaries,
+    spins: Box<[S]>,
 }
 
-impl Lattice for Lattice2D {
+impl<S: Spin> Lattice2D<S> {
+    /// Returns the index of the neighbour of the spin at index i along the given axis, which is
+    /// 0 for the x and 1 for the y direction, and the phase of the bond. Returns none if the
+    /// bond crosses a free boundary.
+    fn shift(&self, i: usize, axis: usize, forward: bool) -> Option<(usize, f64)> {
+        let (stride, extent) = [(1, self.length), (self.length, self.height)][axis];
+        self.boundaries.shift(i, axis, stride, extent, forward)
+    }
+
+    /// Calculates the winding number of the plaquette spanned by the spin at index i and its
+    /// right, upper right and upper neighbours. Returns +1 for a vortex, -1 for an antivortex
+    /// and 0 otherwise, which includes plaquettes cut by a free boundary.
+    pub fn winding(&self, i: usize) -> i32 {
+        // Walk around the plaquette and add up the angle differences wrapped into [-pi, pi)
+        let (mut corner, mut total) = (i, 0.0);
+        for (axis, forward) in [(0, true), (1, true), (0, false), (1, false)] {
+            let Some((next, phase)) = self.shift(corner, axis, forward) else {
+                return 0;
+            };
+            let diff = self.angle(next) + phase - self.angle(corner);
+            total += (diff + PI).rem_euclid(MAX_ANGLE) - PI;
+            corner = next;
+        }
+        (total / MAX_ANGLE).round() as i32
+    }
+
+    /// Finds the vortices and antivortices by computing the winding number of every plaquette.
+    /// Returns the positions of both, where a plaquette is given by the index of the spin at its
+    /// lower left corner.
+    pub fn vortices(&self) -> (Vec<usize>, Vec<usize>) {
+        let (mut vortices, mut antivortices) = (Vec::new(), Vec::new());
+        for i in 0..self.sites() {
+            match self.winding(i) {
+                w if w > 0 => vortices.push(i),
+                w if w < 0 => antivortices.push(i),
+                _ => {}
+            }
+        }
+        (vortices, antivortices)
+    }
+}
+
+impl<S: Spin> Lattice for Lattice2D<S> {
     const DIM: usize = 2;
 
-    fn new(length: usize, beta: f64) -> Self {
-        assert_eq!((length * length) % 4, 0);
+    // BKT transition at T = 0.89
+    const CRITICAL_WINDOW: Option<RangeInclusive<f64>> = Some(0.80..=1.2);
+
+    const LANES: usize = S::LANES;
+
+    fn new(length: usize, height: usize, boundaries: Boundaries, beta: f64) -> Self {
         Self {
             beta,
             length,
-            spins: vec![0.0; length * length].into_boxed_slice(),
+            height,
+            boundaries,
+            spins: vec![S::from_angle(0.0); length * height].into_boxed_slice(),
         }
     }
 
@@ -24,6 +75,10 @@ impl Lattice for Lattice2D {
         self.beta = beta;
     }
 
+    fn beta(&self) -> f64 {
+        self.beta
+    }
+
     fn temperature(&self) -> f64 {
         self.beta.recip()
     }
@@ -32,45 +87,58 @@ impl Lattice for Lattice2D {
         self.spins.len()
     }
 
+    fn angle(&self, i: usize) -> f64 {
+        self[i].angle()
+    }
+
+    fn round_angle(&self, angle: f64) -> f64 {
+        S::from_angle(angle).angle()
+    }
+
     fn update_angle(&mut self, i: usize, angle: f64) {
-        self.spins[i] = angle;
+        self.spins[i] = S::from_angle(angle);
     }
 
     fn energy(&self) -> f64 {
-        let mut result = 0.0;
-        for i in (0..self.sites()).step_by(2) {
-            let old = f64x4::new([self[i], self[i], self[i + 1], self[i + 1]]);
-            let neighbours = f64x4::new([
-                self[(i + 1) % self.sites()],
-                self[(i + self.length) % self.sites()],
-                self[(i + 2) % self.sites()],
-                self[(i + 1 + self.length) % self.sites()],
-            ]);
-            result += (old - neighbours).cos().reduce_add();
-        }
-        -result
+        let bonds = (0..self.sites()).flat_map(|i| {
+            [0, 1]
+                .into_iter()
+                .filter_map(move |axis| self.shift(i, axis, true))
+                .map(move |(j, phase)| (self[i], self[j].rotate(phase)))
+        });
+        -bond_sum(bonds)
     }
 
-    fn energy_diff(&self, i: usize, angle: f64) -> f64 {
-        let neighbours = f64x4::from([
-            self[(i + 1) % self.sites()],
-            self[(i + self.sites() - 1) % self.sites()],
-            self[(i + self.length) % self.sites()],
-            self[(i + self.sites() - self.length) % self.sites()],
-        ]);
+    fn neighbours(&self, i: usize) -> impl Iterator<Item = (usize, f64)> {
+        [
+            self.shift(i, 0, true),
+            self.shift(i, 0, false),
+            self.shift(i, 1, true),
+            self.shift(i, 1, false),
+        ]
+        .into_iter()
+        .flatten()
+    }
 
-        let old = f64x4::splat(self[i]);
-        let before = (old - neighbours).cos().reduce_add();
+    fn energy_diff(&self, i: usize, angle: f64) -> f64 {
+        let neighbours = self.neighbours(i).map(|(j, phase)| self[j].rotate(phase));
+        bond_diff(self[i], S::from_angle(angle), neighbours)
+    }
 
-        let new = f64x4::splat(angle);
-        let after = (new - neighbours).cos().reduce_add();
+    fn magnetization_diff(&self, i: usize, angle: f64) -> (f64, f64) {
+        S::magnetization_diff(self[i], S::from_angle(angle))
+    }
 
-        before - after
+    fn helicity(&self) -> (f64, f64) {
+        helicity_sum((0..self.sites()).filter_map(|i| {
+            self.shift(i, 0, true)
+                .map(|(j, phase)| (self.angle(i), self.angle(j) + phase, 1.0))
+        }))
     }
 
-    fn magnetization_diff(&self, i: usize, angle: f64) -> (f64, f64) {
-        let (sin, cos) = f64x2::from([angle, std::f64::consts::PI + self[i]]).sin_cos();
-        (cos.reduce_add(), sin.reduce_add())
+    fn vortex_density(&self) -> Option<f64> {
+        let count = (0..self.sites()).filter(|i| self.winding(*i) != 0).count();
+        Some(count as f64 / self.sites() as f64)
     }
 
     fn acceptance(&self, diff_energy: f64) -> f64 {
@@ -78,12 +146,13 @@ impl Lattice for Lattice2D {
     }
 
     fn serialize(&self) -> String {
-        serde_json::to_string(&self.spins).unwrap()
+        let angles = self.spins.iter().map(|s| s.angle()).collect::<Vec<_>>();
+        serde_json::to_string(&angles).unwrap()
     }
 }
 
-impl Index<usize> for Lattice2D {
-    type Output = f64;
+impl<S> Index<usize> for Lattice2D<S> {
+    type Output = S;
 
     fn index(&self, index: usize) -> &Self::Output {
         &self.spins[index]
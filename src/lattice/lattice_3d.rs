@@ -0,0 +1,124 @@
+use crate::lattice::{bond_diff, bond_sum, helicity_sum, Boundaries, Lattice, Spin};
+use std::ops::{Index, RangeInclusive};
+
+pub struct Lattice3D<S = f64> {
+    beta: f64,
+    length: usize,
+    height: usize,
+    boundaries: Boundaries,
+    spins: Box<[S]>,
+}
+
+impl<S: Spin> Lattice3D<S> {
+    /// Returns the index of the neighbour of the spin at index i along the given axis, which is
+    /// 0, 1 or 2 for the x, y and z direction, and the phase of the bond. Returns none if the
+    /// bond crosses a free boundary.
+    fn shift(&self, i: usize, axis: usize, forward: bool) -> Option<(usize, f64)> {
+        let stride = [1, self.length, self.length.pow(2)][axis];
+        let extent = [self.length, self.length, self.height][axis];
+        self.boundaries.shift(i, axis, stride, extent, forward)
+    }
+}
+
+impl<S: Spin> Lattice for Lattice3D<S> {
+    const DIM: usize = 3;
+
+    // Transition at T = 2.20
+    const CRITICAL_WINDOW: Option<RangeInclusive<f64>> = Some(2.0..=2.4);
+
+    const LANES: usize = S::LANES;
+
+    fn new(length: usize, height: usize, boundaries: Boundaries, beta: f64) -> Self {
+        Self {
+            beta,
+            length,
+            height,
+            boundaries,
+            spins: vec![S::from_angle(0.0); length * length * height].into_boxed_slice(),
+        }
+    }
+
+    fn set_beta(&mut self, beta: f64) {
+        self.beta = beta;
+    }
+
+    fn beta(&self) -> f64 {
+        self.beta
+    }
+
+    fn temperature(&self) -> f64 {
+        self.beta.recip()
+    }
+
+    fn sites(&self) -> usize {
+        self.spins.len()
+    }
+
+    fn angle(&self, i: usize) -> f64 {
+        self[i].angle()
+    }
+
+    fn round_angle(&self, angle: f64) -> f64 {
+        S::from_angle(angle).angle()
+    }
+
+    fn update_angle(&mut self, i: usize, angle: f64) {
+        self.spins[i] = S::from_angle(angle);
+    }
+
+    fn energy(&self) -> f64 {
+        let bonds = (0..self.sites()).flat_map(|i| {
+            [0, 1, 2]
+                .into_iter()
+                .filter_map(move |axis| self.shift(i, axis, true))
+                .map(move |(j, phase)| (self[i], self[j].rotate(phase)))
+        });
+        -bond_sum(bonds)
+    }
+
+    fn neighbours(&self, i: usize) -> impl Iterator<Item = (usize, f64)> {
+        [
+            self.shift(i, 0, true),
+            self.shift(i, 0, false),
+            self.shift(i, 1, true),
+            self.shift(i, 1, false),
+            self.shift(i, 2, true),
+            self.shift(i, 2, false),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    fn energy_diff(&self, i: usize, angle: f64) -> f64 {
+        let neighbours = self.neighbours(i).map(|(j, phase)| self[j].rotate(phase));
+        bond_diff(self[i], S::from_angle(angle), neighbours)
+    }
+
+    fn magnetization_diff(&self, i: usize, angle: f64) -> (f64, f64) {
+        S::magnetization_diff(self[i], S::from_angle(angle))
+    }
+
+    fn helicity(&self) -> (f64, f64) {
+        helicity_sum((0..self.sites()).filter_map(|i| {
+            self.shift(i, 0, true)
+                .map(|(j, phase)| (self.angle(i), self.angle(j) + phase, 1.0))
+        }))
+    }
+
+    fn acceptance(&self, diff_energy: f64) -> f64 {
+        f64::min(1.0, f64::exp(-self.beta * diff_energy))
+    }
+
+    fn serialize(&self) -> String {
+        let angles = self.spins.iter().map(|s| s.angle()).collect::<Vec<_>>();
+        serde_json::to_string(&angles).unwrap()
+    }
+}
+
+impl<S> Index<usize> for Lattice3D<S> {
+    type Output = S;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.spins[index]
+    }
+}
@@ -1,35 +1,157 @@
 use crate::analysis::Observable;
-use std::ops::Index;
+use crate::constants::MAX_ANGLE;
+use std::ops::RangeInclusive;
 use wide::f64x4;
 
+pub mod boundary;
+pub mod clock;
+pub mod honeycomb;
 pub mod lattice_1d;
 pub mod lattice_2d;
+pub mod lattice_3d;
+pub mod spin;
+pub mod triangular;
 
+pub use boundary::{Boundaries, Boundary};
+pub use clock::ClockLattice;
+pub use honeycomb::HoneycombLattice;
 pub use lattice_1d::Lattice1D;
 pub use lattice_2d::Lattice2D;
+pub use lattice_3d::Lattice3D;
+pub use spin::{Discrete, Precision, Spin};
+pub use triangular::TriangularLattice;
+
+/// The geometries of the two dimensional lattices.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Geometry {
+    /// Square lattice with four neighbours.
+    Square,
+    /// Triangular lattice with six neighbours.
+    Triangular,
+    /// Honeycomb lattice with three neighbours.
+    Honeycomb,
+}
+
+/// Sums cos(a - b) over all given pairs of spins, evaluating eight pairs per SIMD call. Unused
+/// lanes of the last call compare a spin with itself and are subtracted again.
+pub fn bond_sum<S: Spin>(pairs: impl Iterator<Item = (S, S)>) -> f64 {
+    let zero = S::from_angle(0.0);
+    let (mut a, mut b, mut lanes, mut result) = ([zero; 8], [zero; 8], 0, 0.0);
+    for (x, y) in pairs {
+        (a[lanes], b[lanes]) = (x, y);
+        lanes += 1;
+        if lanes == 8 {
+            let (first, second) = S::bonds(a, b);
+            (a, b, lanes) = ([zero; 8], [zero; 8], 0);
+            result += first + second;
+        }
+    }
+
+    let (first, second) = S::bonds(a, b);
+    result + first + second - (8 - lanes) as f64
+}
+
+/// Calculates the energy difference if the spin old was to change to new given the spins of its
+/// neighbours, evaluating four neighbours per SIMD call. Unused lanes compare the old spin with
+/// itself before and the new spin with itself after the change, so they cancel.
+pub fn bond_diff<S: Spin>(old: S, new: S, neighbours: impl Iterator<Item = S>) -> f64 {
+    let a = [old, old, old, old, new, new, new, new];
+    let (mut b, mut lanes, mut result) = (a, 0, 0.0);
+    for spin in neighbours {
+        (b[lanes], b[lanes + 4]) = (spin, spin);
+        lanes += 1;
+        if lanes == 4 {
+            let (before, after) = S::bonds(a, b);
+            (b, lanes) = (a, 0);
+            result += before - after;
+        }
+    }
 
-pub trait Lattice: Index<usize, Output = f64> {
+    if lanes > 0 {
+        let (before, after) = S::bonds(a, b);
+        result += before - after;
+    }
+    result
+}
+
+/// Sums cos(a - b) weighted by the squared projection and sin(a - b) weighted by the projection
+/// over the given bonds, which are the coupling and the current of the bonds entering the
+/// helicity modulus. The projection is the x component of the unit bond vector from a to b.
+pub fn helicity_sum(bonds: impl Iterator<Item = (f64, f64, f64)>) -> (f64, f64) {
+    bonds.fold((0.0, 0.0), |(cos, sin), (a, b, projection)| {
+        let (s, c) = (a - b).sin_cos();
+        (cos + c * projection.powi(2), sin + s * projection)
+    })
+}
+
+pub trait Lattice: Sync {
     /// The dimensionality of the lattice.
     const DIM: usize;
 
-    /// Instantiates a new lattice with side length and beta
-    fn new(length: usize, beta: f64) -> Self;
+    /// The temperatures around the transition of the lattice, within which the zoom searches for
+    /// the peak of the magnetic susceptibility. Lattices without a transition at a finite
+    /// temperature have none and are only scanned once over the full temperature range.
+    const CRITICAL_WINDOW: Option<RangeInclusive<f64>>;
+
+    /// The area or volume per site in units of the squared or cubed bond length, which
+    /// normalizes the helicity modulus to the stiffness per unit area or volume.
+    const VOLUME_PER_SITE: f64 = 1.0;
+
+    /// The number of sites the vectorized sweep updates at once in the SIMD lanes.
+    const LANES: usize = 4;
+
+    /// Instantiates a new lattice with side length, height, boundary conditions and beta. The
+    /// height is the extent along the last direction, so 2D lattices span length x height and 3D
+    /// lattices span length x length x height. One dimensional lattices require a height of one.
+    fn new(length: usize, height: usize, boundaries: Boundaries, beta: f64) -> Self;
 
     /// Sets a new beta for the lattice.
     fn set_beta(&mut self, beta: f64);
 
+    /// Returns the beta of the lattice.
+    fn beta(&self) -> f64;
+
     /// Returns the temperature of the lattice
     fn temperature(&self) -> f64;
 
     /// Returns the number of lattice sites.
     fn sites(&self) -> usize;
 
+    /// Returns the angle of the spin at index i.
+    fn angle(&self, i: usize) -> f64;
+
+    /// Rounds the given angle to the representation stored by update_angle.
+    fn round_angle(&self, angle: f64) -> f64;
+
+    /// Proposes a new angle for the spin at index i uniformly within the given width around its
+    /// current angle. The proposal must be symmetric for the Metropolis acceptance to hold.
+    fn propose(&self, rng: &mut fastrand::Rng, i: usize, width: f64) -> f64 {
+        (self.angle(i) + width * (2.0 * rng.f64() - 1.0)).rem_euclid(MAX_ANGLE)
+    }
+
     /// Updates the angle of the spin at index i.
     fn update_angle(&mut self, i: usize, angle: f64);
 
     /// Calculates the total energy of the lattice.
     fn energy(&self) -> f64;
 
+    /// Returns the indices of the nearest neighbours of the spin at index i together with the
+    /// phase of each bond, which is added to the angle of the neighbour. The phase is zero
+    /// unless the bond crosses a twisted boundary, neighbours behind free boundaries are left out.
+    fn neighbours(&self, i: usize) -> impl Iterator<Item = (usize, f64)>;
+
+    /// Calculates the local field acting on the spin at index i by adding up the cosine and sine
+    /// of all neighbouring angles. Returns the cosine and sine component of the field.
+    fn local_field(&self, i: usize) -> (f64, f64) {
+        let (mut cos, mut sin) = (0.0, 0.0);
+        for (j, phase) in self.neighbours(i) {
+            let (s, c) = (self.angle(j) + phase).sin_cos();
+            cos += c;
+            sin += s;
+        }
+        (cos, sin)
+    }
+
     /// Calculates the energy difference if one was to flip the spin at index i.
     fn energy_diff(&self, i: usize, angle: f64) -> f64;
 
@@ -39,18 +161,39 @@ pub trait Lattice: Index<usize, Output = f64> {
     fn magnetization(&self) -> (f64, f64) {
         let (mut cos, mut sin) = (0.0, 0.0);
         for i in (0..self.sites()).step_by(4) {
-            let (s, c) = f64x4::new([self[i], self[i + 1], self[i + 2], self[i + 3]]).sin_cos();
+            // Lanes beyond the last site point along the x axis and are subtracted again
+            let angles = [i, i + 1, i + 2, i + 3].map(|j| {
+                if j < self.sites() {
+                    self.angle(j)
+                } else {
+                    0.0
+                }
+            });
+            let (s, c) = f64x4::new(angles).sin_cos();
 
             cos += c.reduce_add();
             sin += s.reduce_add();
         }
-        (cos, sin)
+        let padding = (4 - self.sites() % 4) % 4;
+        (cos - padding as f64, sin)
     }
 
     /// Calculates the magnetization difference if one was to change the spin at index i.
     /// Returns the cosine and sine component of the magnetization.
     fn magnetization_diff(&self, i: usize, angle: f64) -> (f64, f64);
 
+    /// Calculates the coupling and the current along the x direction, which are the sums of
+    /// cos(a - b) and sin(a - b) over all bonds with a component along x, weighted by the squared
+    /// and the plain x component of the unit bond vector. Bonds across a twisted boundary
+    /// include their twist.
+    fn helicity(&self) -> (f64, f64);
+
+    /// Calculates the number of vortices and antivortices per lattice site. Returns none for
+    /// lattices without vortex detection.
+    fn vortex_density(&self) -> Option<f64> {
+        None
+    }
+
     /// Calculates the acceptance probability if one was to flip the spin at index i.
     fn acceptance(&self, diff_energy: f64) -> f64;
 
@@ -77,6 +220,21 @@ pub trait Lattice: Index<usize, Output = f64> {
         )
     }
 
+    /// Calculates the helicity modulus along the x direction per unit area or volume from the
+    /// coupling and the current, where the fluctuation of the current is taken about its mean so
+    /// that twisted boundaries with a persistent current are handled as well.
+    fn helicity_modulus(&self, coupling: &Observable, current: &Observable) -> (f64, f64) {
+        let volume = self.sites() as f64 * Self::VOLUME_PER_SITE;
+        (
+            (coupling.mean - self.beta() * (current.sqr_mean - current.mean.powi(2))) / volume,
+            (coupling.stddev.powi(2)
+                + (self.beta() * current.sqr_stddev).powi(2)
+                + (2.0 * self.beta() * current.mean * current.stddev).powi(2))
+            .sqrt()
+                / volume,
+        )
+    }
+
     /// Serializes the spins into JSON array
     fn serialize(&self) -> String;
 }
@@ -0,0 +1,187 @@
+use crate::constants::MAX_ANGLE;
+use std::f64::consts::PI;
+use wide::{f32x4, f32x8, f64x2, f64x4};
+
+/// The precision the spins of the lattices are stored and evaluated in.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Precision {
+    /// Spins and local computations in f64.
+    Double,
+    /// Spins and local computations in f32, doubling the SIMD width. Observables are still
+    /// accumulated in f64.
+    Single,
+    /// Angles restricted to 1024 values, stored as indices into cos and sin lookup tables.
+    Discrete1024,
+    /// Angles restricted to 65536 values, stored as indices into cos and sin lookup tables.
+    Discrete65536,
+}
+
+/// The representation in which the lattices store their spins. It provides the SIMD kernels
+/// which evaluate the bond energies cos(a - b) in this representation, while all sums over
+/// bonds are accumulated in f64.
+pub trait Spin: Copy + Send + Sync {
+    /// The number of sites the vectorized sweep updates at once, which follows the width of the
+    /// precision its lanes are calculated in.
+    const LANES: usize = 4;
+
+    /// Converts an angle into the stored representation.
+    fn from_angle(angle: f64) -> Self;
+
+    /// Converts the stored representation back into an angle.
+    fn angle(self) -> f64;
+
+    /// Rotates the spin by the given phase, leaving it untouched if the phase is zero.
+    fn rotate(self, phase: f64) -> Self {
+        if phase == 0.0 {
+            self
+        } else {
+            Self::from_angle(self.angle() + phase)
+        }
+    }
+
+    /// Calculates cos(a - b) for two sets of four angle pairs at once. Returns the sum over each
+    /// of the sets.
+    fn bonds(a: [Self; 8], b: [Self; 8]) -> (f64, f64);
+
+    /// Calculates cos(a - b) for two sets of two angle pairs at once. Returns the sum over each
+    /// of the sets.
+    fn bond_pairs(a: [Self; 4], b: [Self; 4]) -> (f64, f64);
+
+    /// Calculates the change of the cosine and sine component of the magnetization if the spin
+    /// was to change from old to new.
+    fn magnetization_diff(old: Self, new: Self) -> (f64, f64) {
+        let (sin, cos) = f64x2::from([new.angle(), PI + old.angle()]).sin_cos();
+        (cos.reduce_add(), sin.reduce_add())
+    }
+}
+
+impl Spin for f64 {
+    fn from_angle(angle: f64) -> Self {
+        angle
+    }
+
+    fn angle(self) -> f64 {
+        self
+    }
+
+    fn bonds(a: [Self; 8], b: [Self; 8]) -> (f64, f64) {
+        let first = f64x4::new([a[0], a[1], a[2], a[3]]) - f64x4::new([b[0], b[1], b[2], b[3]]);
+        let second = f64x4::new([a[4], a[5], a[6], a[7]]) - f64x4::new([b[4], b[5], b[6], b[7]]);
+        (first.cos().reduce_add(), second.cos().reduce_add())
+    }
+
+    fn bond_pairs(a: [Self; 4], b: [Self; 4]) -> (f64, f64) {
+        let bonds = (f64x4::new(a) - f64x4::new(b)).cos().to_array();
+        (bonds[0] + bonds[1], bonds[2] + bonds[3])
+    }
+}
+
+impl Spin for f32 {
+    const LANES: usize = 8;
+
+    fn from_angle(angle: f64) -> Self {
+        angle as f32
+    }
+
+    fn angle(self) -> f64 {
+        self as f64
+    }
+
+    fn bonds(a: [Self; 8], b: [Self; 8]) -> (f64, f64) {
+        let bonds = (f32x8::new(a) - f32x8::new(b))
+            .cos()
+            .to_array()
+            .map(f64::from);
+        (
+            bonds[0] + bonds[1] + bonds[2] + bonds[3],
+            bonds[4] + bonds[5] + bonds[6] + bonds[7],
+        )
+    }
+
+    fn bond_pairs(a: [Self; 4], b: [Self; 4]) -> (f64, f64) {
+        let bonds = (f32x4::new(a) - f32x4::new(b))
+            .cos()
+            .to_array()
+            .map(f64::from);
+        (bonds[0] + bonds[1], bonds[2] + bonds[3])
+    }
+}
+
+/// A spin whose angle is restricted to the N values 2πk/N, which is the N-state clock model.
+/// The index k is stored instead of the angle and the cosine and sine of the bond energies are
+/// looked up from tables. Proposed angles are rounded to the nearest allowed value, so the
+/// algorithms which reflect or integrate angles no longer obey detailed balance exactly.
+#[derive(Clone, Copy)]
+pub struct Discrete<const N: usize>(u32);
+
+impl<const N: usize> Discrete<N> {
+    /// The cosine of all allowed angles.
+    const COS: &'static [f64; N] = &cosines::<N>(0.0);
+
+    /// The sine of all allowed angles.
+    const SIN: &'static [f64; N] = &cosines::<N>(PI / 2.0);
+
+    /// Looks up the cosine of the angle between two spins.
+    fn bond(a: Self, b: Self) -> f64 {
+        let k = a.0 as usize + N - b.0 as usize;
+        Self::COS[if k >= N { k - N } else { k }]
+    }
+}
+
+/// Evaluates cos(2πk/N - phase) for all k at compile time, since the float functions of the
+/// standard library are not const. After reducing the argument into [-π, π] the Taylor series
+/// converges to machine precision.
+pub(crate) const fn cosines<const N: usize>(phase: f64) -> [f64; N] {
+    let mut table = [0.0; N];
+    let mut k = 0;
+    while k < N {
+        let mut x = MAX_ANGLE * k as f64 / N as f64 - phase;
+        if x > PI {
+            x -= MAX_ANGLE;
+        } else if x < -PI {
+            x += MAX_ANGLE;
+        }
+
+        let (mut term, mut sum, mut n) = (1.0, 1.0, 1.0);
+        while n < 40.0 {
+            term *= -x * x / (n * (n + 1.0));
+            sum += term;
+            n += 2.0;
+        }
+        table[k] = sum;
+        k += 1;
+    }
+    table
+}
+
+impl<const N: usize> Spin for Discrete<N> {
+    fn from_angle(angle: f64) -> Self {
+        let k = (angle.rem_euclid(MAX_ANGLE) * N as f64 / MAX_ANGLE).round() as usize;
+        Self((k % N) as u32)
+    }
+
+    fn angle(self) -> f64 {
+        MAX_ANGLE * self.0 as f64 / N as f64
+    }
+
+    fn bonds(a: [Self; 8], b: [Self; 8]) -> (f64, f64) {
+        let bonds = std::array::from_fn::<_, 8, _>(|k| Self::bond(a[k], b[k]));
+        (
+            bonds[0] + bonds[1] + bonds[2] + bonds[3],
+            bonds[4] + bonds[5] + bonds[6] + bonds[7],
+        )
+    }
+
+    fn bond_pairs(a: [Self; 4], b: [Self; 4]) -> (f64, f64) {
+        let bonds = std::array::from_fn::<_, 4, _>(|k| Self::bond(a[k], b[k]));
+        (bonds[0] + bonds[1], bonds[2] + bonds[3])
+    }
+
+    fn magnetization_diff(old: Self, new: Self) -> (f64, f64) {
+        let (old, new) = (old.0 as usize, new.0 as usize);
+        (
+            Self::COS[new] - Self::COS[old],
+            Self::SIN[new] - Self::SIN[old],
+        )
+    }
+}
@@ -0,0 +1,138 @@
+use crate::lattice::{bond_diff, bond_sum, helicity_sum, Boundaries, Lattice, Spin};
+use std::ops::{Index, RangeInclusive};
+
+/// The triangular lattice, which is the square lattice with an additional bond along one
+/// diagonal of every plaquette. Every spin has six neighbours.
+pub struct TriangularLattice<S = f64> {
+    beta: f64,
+    length: usize,
+    height: usize,
+    boundaries: Boundaries,
+    spins: Box<[S]>,
+}
+
+impl<S: Spin> TriangularLattice<S> {
+    /// Returns the index of the neighbour of the spin at index i along the x axis, the y axis
+    /// or the diagonal in between, and the phase of the bond. The diagonal bond picks up the
+    /// phases of both boundaries. Returns none if the bond crosses a free boundary.
+    fn shift(&self, i: usize, axes: &[usize], forward: bool) -> Option<(usize, f64)> {
+        axes.iter().try_fold((i, 0.0), |(j, phase), &axis| {
+            let (stride, extent) = [(1, self.length), (self.length, self.height)][axis];
+            let (k, twist) = self.boundaries.shift(j, axis, stride, extent, forward)?;
+            Some((k, phase + twist))
+        })
+    }
+}
+
+impl<S: Spin> Lattice for TriangularLattice<S> {
+    const DIM: usize = 2;
+
+    // BKT transition at T = 1.47
+    const CRITICAL_WINDOW: Option<RangeInclusive<f64>> = Some(1.3..=1.7);
+
+    // √3 / 2
+    const VOLUME_PER_SITE: f64 = 0.8660254037844386;
+
+    const LANES: usize = S::LANES;
+
+    fn new(length: usize, height: usize, boundaries: Boundaries, beta: f64) -> Self {
+        Self {
+            beta,
+            length,
+            height,
+            boundaries,
+            spins: vec![S::from_angle(0.0); length * height].into_boxed_slice(),
+        }
+    }
+
+    fn set_beta(&mut self, beta: f64) {
+        self.beta = beta;
+    }
+
+    fn beta(&self) -> f64 {
+        self.beta
+    }
+
+    fn temperature(&self) -> f64 {
+        self.beta.recip()
+    }
+
+    fn sites(&self) -> usize {
+        self.spins.len()
+    }
+
+    fn angle(&self, i: usize) -> f64 {
+        self[i].angle()
+    }
+
+    fn round_angle(&self, angle: f64) -> f64 {
+        S::from_angle(angle).angle()
+    }
+
+    fn update_angle(&mut self, i: usize, angle: f64) {
+        self.spins[i] = S::from_angle(angle);
+    }
+
+    fn energy(&self) -> f64 {
+        let bonds = (0..self.sites()).flat_map(|i| {
+            [&[0][..], &[1], &[0, 1]]
+                .into_iter()
+                .filter_map(move |axes| self.shift(i, axes, true))
+                .map(move |(j, phase)| (self[i], self[j].rotate(phase)))
+        });
+        -bond_sum(bonds)
+    }
+
+    fn neighbours(&self, i: usize) -> impl Iterator<Item = (usize, f64)> {
+        [
+            self.shift(i, &[0], true),
+            self.shift(i, &[0], false),
+            self.shift(i, &[1], true),
+            self.shift(i, &[1], false),
+            self.shift(i, &[0, 1], true),
+            self.shift(i, &[0, 1], false),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    fn energy_diff(&self, i: usize, angle: f64) -> f64 {
+        let neighbours = self.neighbours(i).map(|(j, phase)| self[j].rotate(phase));
+        bond_diff(self[i], S::from_angle(angle), neighbours)
+    }
+
+    fn magnetization_diff(&self, i: usize, angle: f64) -> (f64, f64) {
+        S::magnetization_diff(self[i], S::from_angle(angle))
+    }
+
+    fn helicity(&self) -> (f64, f64) {
+        // The y axis points at 120° to the x axis, so the bonds along y and along the diagonal
+        // advance by half a bond length in negative and positive x direction
+        let bonds = (0..self.sites()).flat_map(|i| {
+            [(&[0][..], 1.0), (&[1], -0.5), (&[0, 1], 0.5)]
+                .into_iter()
+                .filter_map(move |(axes, projection)| {
+                    self.shift(i, axes, true)
+                        .map(|(j, phase)| (self.angle(i), self.angle(j) + phase, projection))
+                })
+        });
+        helicity_sum(bonds)
+    }
+
+    fn acceptance(&self, diff_energy: f64) -> f64 {
+        f64::min(1.0, f64::exp(-self.beta * diff_energy))
+    }
+
+    fn serialize(&self) -> String {
+        let angles = self.spins.iter().map(|s| s.angle()).collect::<Vec<_>>();
+        serde_json::to_string(&angles).unwrap()
+    }
+}
+
+impl<S> Index<usize> for TriangularLattice<S> {
+    type Output = S;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.spins[index]
+    }
+}
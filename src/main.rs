@@ -3,9 +3,22 @@ use rayon::prelude::*;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use crate::algorithm::Algorithm;
-use crate::lattice::{Lattice, Lattice1D, Lattice2D};
-use crate::storage::Configuration;
+use crate::algorithm::annealing::Annealing;
+use crate::algorithm::checkerboard::coloring;
+use crate::algorithm::metropolis::Metropolis;
+use crate::algorithm::multicanonical::Multicanonical;
+use crate::algorithm::quench::Schedule;
+use crate::algorithm::tempering::parallel_tempering;
+use crate::algorithm::vectorized::{SimdRng, Vectorized};
+use crate::algorithm::wang_landau::WangLandau;
+use crate::algorithm::{Algorithm, Chain, Parameters, Series};
+use crate::analysis::reweighting::{Histogram, Reweighted};
+use crate::analysis::DensityOfStates;
+use crate::lattice::{
+    Boundaries, ClockLattice, Discrete, Geometry, HoneycombLattice, Lattice, Lattice1D, Lattice2D,
+    Lattice3D, Precision, Spin, TriangularLattice,
+};
+use crate::storage::{Configuration, Exchange, Observation, Quench, Snapshot};
 use crate::utils::{host, range, range_par};
 
 mod algorithm;
@@ -31,60 +44,251 @@ const MAX_DEPTH: usize = 2;
 /// The total number of distinct temperature values.
 const TOTAL: usize = MAX_DEPTH * STEPS;
 
+/// The number of energy bins for the Wang Landau density of states.
+const BINS: usize = 256;
+
+/// The fraction of the mean histogram entry every visited bin must reach to be considered flat.
+const FLATNESS: f64 = 0.8;
+
+/// The log modification factor below which the Wang Landau iteration stops.
+const MIN_FACTOR: f64 = 1e-6;
+
+/// The number of resamples (B) for the bootstrap analysis of reweighted observables.
+const REWEIGHT_RESAMPLES: usize = 1_000;
+
+/// The number of temperatures the histograms of the final zoom are reweighted to.
+const REWEIGHT_STEPS: usize = 256;
+
+/// The number of equally spaced times at which the vortex density of a quench is recorded.
+const QUENCH_RECORDS: usize = 256;
+
+/// The temperature close to the BKT transition at which the throughput is benchmarked.
+const BENCHMARK_TEMPERATURE: f64 = 0.89;
+
+/// The number of MC sweeps timed by the throughput benchmark.
+const BENCHMARK_SWEEPS: usize = 1_000;
+
+/// The temperatures at which single and double precision observables are compared.
+const VALIDATION_TEMPERATURES: [f64; 6] = [0.5, 0.8, 0.9, 1.0, 1.2, 2.0];
+
+/// The number of MC sweeps per temperature and precision of the validation.
+const VALIDATION_SWEEPS: usize = 100_000;
+
+/// Performs the bootstrap analysis on the observables of a simulated lattice. Returns the final
+/// configuration with the following observables. e, e^2, m, m^2, Cv, Xs. Also returns the thinned
+/// series for the reweighting to nearby temperatures.
+fn analyze<L>(
+    counter: &AtomicUsize,
+    size: usize,
+    lattice: &L,
+    rng: &mut fastrand::Rng,
+    series: Series,
+    time_mc: u128,
+) -> (Configuration, Histogram)
+where
+    L: Lattice,
+{
+    // Keep thinned series for the reweighting
+    let histogram = Histogram::new(
+        lattice.temperature(),
+        lattice.sites(),
+        &series.energies,
+        &series.magnets,
+    );
+
+    // Perform bootstrap analysis on observables
+    let start = std::time::Instant::now();
+    let e = analysis::complete(rng, series.energies, RESAMPLES);
+    let m = analysis::complete(rng, series.magnets, RESAMPLES);
+    let h = (
+        analysis::complete(rng, series.couplings, RESAMPLES),
+        analysis::complete(rng, series.currents, RESAMPLES),
+    );
+    let v =
+        (!series.vortices.is_empty()).then(|| analysis::complete(rng, series.vortices, RESAMPLES));
+
+    // Write console information
+    let current = counter.fetch_add(1, Ordering::Relaxed);
+    println!("[{}] D{} L{}: {}/{}", host(), L::DIM, size, current, TOTAL);
+    if let Some(violation) = series.violation {
+        println!(
+            "[{}] D{} L{}: t={:.4} acc={:.4} <dH>={:.6} <dH^2>={:.6} <exp(-dH)>={:.6}",
+            host(),
+            L::DIM,
+            size,
+            lattice.temperature(),
+            series.acceptance,
+            violation.mean,
+            violation.sqr_mean,
+            violation.exp_mean
+        );
+    }
+    if let Some(temperature) = series.temperature {
+        println!(
+            "[{}] D{} L{}: t={:.4} demon t={:.4}",
+            host(),
+            L::DIM,
+            size,
+            lattice.temperature(),
+            temperature
+        );
+    }
+
+    // Serialize spins
+    let time_boot = start.elapsed().as_millis();
+    let configuration = Configuration {
+        vortices: v,
+        violation: series.violation,
+        demon_temperature: series.temperature,
+        ..Configuration::new(
+            lattice,
+            e,
+            m,
+            Some(h),
+            series.acceptance,
+            time_mc,
+            time_boot,
+        )
+    };
+    (configuration, histogram)
+}
+
 /// Simulates the XY model for a given lattice size and temperature. This will do the
 /// metropolis hastings algorithm and the bootstrap analysis on the observables. Returns
-/// the final configuration with the following observables. e, e^2, m, m^2, Cv, Xs.
+/// the final configuration with the following observables. e, e^2, m, m^2, Cv, Xs and the
+/// thinned series.
 fn simulate_size<L>(
     counter: Arc<AtomicUsize>,
+    parameters: Parameters,
     size: usize,
+    height: usize,
+    boundaries: Boundaries,
     rng: &mut fastrand::Rng,
     t: f64,
-) -> Configuration
+) -> (Configuration, Histogram)
 where
     L: Lattice,
 {
     // Initialize lattice
-    let mut lattice = L::new(size, t.recip());
+    let mut lattice = L::new(size, height, boundaries, t.recip());
 
-    // Perform metropolis_hastings and measure time
+    // Perform simulation with all observables and measure time
+    let mut chain = Chain {
+        observables: true,
+        ..Chain::default()
+    };
     let start = std::time::Instant::now();
-    let (energies, magnets) = lattice.simulate(rng, SWEEPS);
+    let series = lattice.simulate(parameters, &mut chain, rng, SWEEPS);
     let time_mc = start.elapsed().as_millis();
 
-    // Perform bootstrap analysis on observables
-    let e = analysis::complete(rng, energies, RESAMPLES);
-    let m = analysis::complete(rng, magnets, RESAMPLES);
+    analyze(&counter, size, &lattice, rng, series, time_mc)
+}
 
-    // Write console information
-    let current = counter.fetch_add(1, Ordering::Relaxed);
-    println!("[{}] D{} L{}: {}/{}", host(), L::DIM, size, current, TOTAL);
+/// Simulates the XY model for a given lattice size and all temperatures at once using parallel
+/// tempering with the given exchange interval. Returns the configurations and thinned series of
+/// all temperatures and the swap acceptance rates of neighbouring temperatures.
+fn simulate_replicas<L>(
+    counter: Arc<AtomicUsize>,
+    parameters: Parameters,
+    size: usize,
+    height: usize,
+    boundaries: Boundaries,
+    temperatures: Vec<f64>,
+    interval: usize,
+) -> (Vec<Configuration>, Vec<Histogram>, Vec<Exchange>)
+where
+    L: Lattice + Send,
+{
+    // Initialize one replica per temperature
+    let mut rng = fastrand::Rng::new();
+    let mut replicas = temperatures
+        .iter()
+        .map(|t| L::new(size, height, boundaries, t.recip()))
+        .collect::<Vec<_>>();
 
-    // Serialize spins
-    let time_boot = start.elapsed().as_millis() - time_mc;
-    Configuration::new(&lattice, e, m, time_mc, time_boot)
+    // Perform parallel tempering and measure time
+    let start = std::time::Instant::now();
+    let (series, rates) = parallel_tempering(&mut rng, parameters, &mut replicas, SWEEPS, interval);
+    let time_mc = start.elapsed().as_millis();
+
+    // Perform bootstrap analysis for all temperatures in parallel
+    let (configs, histograms) = replicas
+        .par_iter()
+        .zip(series)
+        .map_init(fastrand::Rng::new, |rng, (lattice, series)| {
+            analyze(&counter, size, lattice, rng, series, time_mc)
+        })
+        .unzip();
+
+    // Pair swap acceptance rates with their temperatures
+    let exchanges = temperatures
+        .windows(2)
+        .zip(rates)
+        .map(|(t, acceptance)| Exchange::new(t[0], t[1], acceptance))
+        .collect();
+
+    (configs, histograms, exchanges)
 }
 
-fn simulate<L>(size: usize) -> Vec<Configuration>
+/// Simulates the XY model for a given lattice size while zooming into the temperature range
+/// around the susceptibility peak. Returns the configurations of all temperatures, the swap
+/// acceptance rates of parallel tempering and the thinned series of the final zoom.
+fn simulate<L>(
+    parameters: Parameters,
+    size: usize,
+    height: usize,
+    boundaries: Boundaries,
+) -> (Vec<Configuration>, Vec<Exchange>, Vec<Histogram>)
 where
-    L: Lattice,
+    L: Lattice + Send,
 {
     // Result set and counter
-    let mut results = Vec::new();
+    let (mut results, mut exchanges, mut histograms) = (Vec::new(), Vec::new(), Vec::new());
     let counter = Arc::new(AtomicUsize::new(1));
 
     // Create initial range and loop trough depth
     let (mut range, mut stride) = range_par(0.0..3.0, STEPS);
     for _ in 0..MAX_DEPTH {
         // Simulate lattice and append results
-        let configs = range.map_init(fastrand::Rng::new, |rng, t| {
-            simulate_size::<L>(counter.clone(), size, rng, t)
-        });
-        results.append(&mut configs.collect::<Vec<_>>());
+        let (mut configs, zoom): (Vec<_>, Vec<_>) = match parameters.tempering {
+            None => range
+                .map_init(fastrand::Rng::new, |rng, t| {
+                    simulate_size::<L>(
+                        counter.clone(),
+                        parameters,
+                        size,
+                        height,
+                        boundaries,
+                        rng,
+                        t,
+                    )
+                })
+                .unzip(),
+            Some(interval) => {
+                let temperatures = range.collect::<Vec<_>>();
+                let (configs, histograms, mut rates) = simulate_replicas::<L>(
+                    counter.clone(),
+                    parameters,
+                    size,
+                    height,
+                    boundaries,
+                    temperatures,
+                    interval,
+                );
+                exchanges.append(&mut rates);
+                (configs, histograms)
+            }
+        };
+        results.append(&mut configs);
+        histograms = zoom;
 
-        // Get top magnetic susceptibility
+        // Get top magnetic susceptibility within the critical window of the lattice
+        let Some(window) = L::CRITICAL_WINDOW else {
+            break;
+        };
         let Some(cfg) = results
             .iter()
-            .filter(Configuration::relevant_tmp)
+            .filter(|cfg| window.contains(&cfg.temperature))
             .max_by(Configuration::cmp)
         else {
             break;
@@ -98,47 +302,533 @@ where
     }
 
     // Order by temperature and remove duplicates
-    results
+    (results, exchanges, histograms)
 }
 
-fn simulate_vortices<L>(size: usize) -> Vec<(f64, String)>
+/// Reweights the thinned series of nearby temperatures to a fine temperature grid spanning them
+/// using the single histogram and the multi histogram method. Returns both reweighted curves or
+/// None if there are no histograms, which happens if the zoom stopped before the first pass.
+fn reweight<L>(size: usize, histograms: &[Histogram]) -> Option<(Reweighted, Reweighted)>
 where
-    L: Lattice
+    L: Lattice,
 {
-    // Initialize random number generator and lattice
+    if histograms.is_empty() {
+        return None;
+    }
+
+    // Fine grid over the range of the simulated temperatures
+    let (min, max) = histograms
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), h| {
+            (f64::min(min, h.temperature), f64::max(max, h.temperature))
+        });
+    let temperatures = range(min..max, REWEIGHT_STEPS).0.collect::<Vec<_>>();
+
+    // Perform reweighting and bootstrap analysis
     let mut rng = fastrand::Rng::new();
-    let mut lattice = L::new(size, 2.0_f64.recip());
+    println!("[{}] D{} L{}: Reweighting histograms", host(), L::DIM, size);
+    let single = analysis::reweighting::single_histogram(
+        &mut rng,
+        histograms,
+        &temperatures,
+        REWEIGHT_RESAMPLES,
+    );
+    let multi = analysis::reweighting::multi_histogram(
+        &mut rng,
+        histograms,
+        &temperatures,
+        REWEIGHT_RESAMPLES,
+    );
+
+    // Write console information
+    println!(
+        "[{}] D{} L{}: Cv peak at t={:.4}({:.4}) Xs peak at t={:.4}({:.4})",
+        host(),
+        L::DIM,
+        size,
+        multi.cv_peak.temperature.0,
+        multi.cv_peak.temperature.1,
+        multi.xs_peak.temperature.0,
+        multi.xs_peak.temperature.1
+    );
+
+    Some((single, multi))
+}
+
+/// Simulates an allocated lattice size and reweights the thinned series of the final zoom.
+/// Returns the configurations of all temperatures, the swap acceptance rates of parallel
+/// tempering and the single and multi histogram reweighted curves, if there are any.
+fn simulate_allocation<L>(
+    parameters: Parameters,
+    size: usize,
+    height: usize,
+    boundaries: Boundaries,
+) -> (Vec<Configuration>, Vec<Exchange>, Option<(Reweighted, Reweighted)>)
+where
+    L: Lattice + Send,
+{
+    let (configurations, exchanges, histograms) =
+        simulate::<L>(parameters, size, height, boundaries);
+    let reweighted = reweight::<L>(size, &histograms);
+    (configurations, exchanges, reweighted)
+}
+
+/// Simulates an allocated size of the XY model with the given dimension and precision of the
+/// spins, where the geometry selects the 2D lattice. See `simulate_allocation`.
+fn simulate_precision(
+    parameters: Parameters,
+    dimension: usize,
+    geometry: Geometry,
+    precision: Precision,
+    size: usize,
+    height: usize,
+    boundaries: Boundaries,
+) -> (Vec<Configuration>, Vec<Exchange>, Option<(Reweighted, Reweighted)>) {
+    match precision {
+        Precision::Double => {
+            simulate_spins::<f64>(parameters, dimension, geometry, size, height, boundaries)
+        }
+        Precision::Single => {
+            simulate_spins::<f32>(parameters, dimension, geometry, size, height, boundaries)
+        }
+        Precision::Discrete1024 => simulate_spins::<Discrete<1024>>(
+            parameters, dimension, geometry, size, height, boundaries,
+        ),
+        Precision::Discrete65536 => simulate_spins::<Discrete<65536>>(
+            parameters, dimension, geometry, size, height, boundaries,
+        ),
+    }
+}
+
+/// Simulates an allocated size of the XY model with spins of type S on the lattice of the given
+/// dimension and geometry. See `simulate_allocation`.
+fn simulate_spins<S>(
+    parameters: Parameters,
+    dimension: usize,
+    geometry: Geometry,
+    size: usize,
+    height: usize,
+    boundaries: Boundaries,
+) -> (Vec<Configuration>, Vec<Exchange>, Option<(Reweighted, Reweighted)>)
+where
+    S: Spin,
+{
+    match (dimension, geometry) {
+        (1, _) => simulate_allocation::<Lattice1D<S>>(parameters, size, height, boundaries),
+        (3, _) => simulate_allocation::<Lattice3D<S>>(parameters, size, height, boundaries),
+        (_, Geometry::Square) => {
+            simulate_allocation::<Lattice2D<S>>(parameters, size, height, boundaries)
+        }
+        (_, Geometry::Triangular) => {
+            simulate_allocation::<TriangularLattice<S>>(parameters, size, height, boundaries)
+        }
+        (_, Geometry::Honeycomb) => {
+            simulate_allocation::<HoneycombLattice<S>>(parameters, size, height, boundaries)
+        }
+    }
+}
+
+/// Simulates an allocated size of the clock model with the given number of states, see
+/// `simulate_allocation`.
+fn simulate_clock(
+    parameters: Parameters,
+    states: usize,
+    size: usize,
+    height: usize,
+    boundaries: Boundaries,
+) -> (Vec<Configuration>, Vec<Exchange>, Option<(Reweighted, Reweighted)>) {
+    match states {
+        2 => simulate_allocation::<ClockLattice<2>>(parameters, size, height, boundaries),
+        3 => simulate_allocation::<ClockLattice<3>>(parameters, size, height, boundaries),
+        4 => simulate_allocation::<ClockLattice<4>>(parameters, size, height, boundaries),
+        5 => simulate_allocation::<ClockLattice<5>>(parameters, size, height, boundaries),
+        6 => simulate_allocation::<ClockLattice<6>>(parameters, size, height, boundaries),
+        8 => simulate_allocation::<ClockLattice<8>>(parameters, size, height, boundaries),
+        12 => simulate_allocation::<ClockLattice<12>>(parameters, size, height, boundaries),
+        16 => simulate_allocation::<ClockLattice<16>>(parameters, size, height, boundaries),
+        _ => unreachable!("unsupported number of clock states {}", states),
+    }
+}
+
+/// Cools the square lattice from hot to cold and lets the remaining vortices dissolve. Returns
+/// the temperature, the spins and the vortex and antivortex positions of every snapshot.
+fn simulate_vortices(parameters: Parameters, size: usize) -> Vec<Snapshot> {
+    // Initialize random number generator, lattice and chain
+    let mut rng = fastrand::Rng::new();
+    let mut lattice = <Lattice2D>::new(size, size, Boundaries::default(), 2.0_f64.recip());
+    let mut chain = Chain::default();
 
     // Thermalize lattice
-    println!("[{}] D{} L{}: Thermalizing lattice vortices", host(), L::DIM, size);
-    lattice.simulate(&mut rng, 100_000);
+    println!(
+        "[{}] D{} L{}: Thermalizing lattice vortices",
+        host(),
+        <Lattice2D>::DIM,
+        size
+    );
+    lattice.simulate(parameters, &mut chain, &mut rng, 100_000);
 
     // Iterate over temperature from hot to cold
     let mut results = Vec::with_capacity(1800);
     for t in range(0.05..1.5, 90).0.rev() {
         // Update the beta value and thermalize
-        println!("[{}] D{} L{}: Vortices for t={:.4}", host(), L::DIM, size, t);
+        println!(
+            "[{}] D{} L{}: Vortices for t={:.4}",
+            host(),
+            <Lattice2D>::DIM,
+            size,
+            t
+        );
         lattice.set_beta(t.recip());
 
         // Thermalize at temperature
         for _ in 0..20 {
-            let _ = lattice.simulate(&mut rng, 1);
-            results.push((t, lattice.serialize()));
+            let _ = lattice.simulate(parameters, &mut chain, &mut rng, 1);
+            let ((vortices, antivortices), spins) = (lattice.vortices(), lattice.serialize());
+            results.push(Snapshot::new(t, spins, vortices, antivortices));
         }
     }
 
     // Allow vortices to dissolve
     for _ in 0..900 {
-        let _ = lattice.simulate(&mut rng, 20);
-        results.push((lattice.temperature(), lattice.serialize()));
+        let _ = lattice.simulate(parameters, &mut chain, &mut rng, 20);
+        let ((vortices, antivortices), spins) = (lattice.vortices(), lattice.serialize());
+        let t = lattice.temperature();
+        results.push(Snapshot::new(t, spins, vortices, antivortices));
+    }
+
+    results
+}
+
+/// Quenches the lattice from infinite temperature and records the time resolved observables
+/// and snapshots of its relaxation, where every sweep advances the time by its time step.
+/// Returns the energy and magnetization per spin after every sweep and the snapshots with their
+/// time.
+fn simulate_dynamics<L>(
+    parameters: Parameters,
+    size: usize,
+) -> (Vec<Observation>, Vec<(f64, String)>)
+where
+    L: Lattice,
+{
+    // Initialize random number generator and lattice at infinite temperature
+    let mut rng = fastrand::Rng::new();
+    let mut lattice = L::new(
+        size,
+        size,
+        Boundaries::default(),
+        parameters.dynamics_temperature.recip(),
+    );
+    for i in 0..lattice.sites() {
+        lattice.update_angle(i, rng.f64() * constants::MAX_ANGLE);
+    }
+
+    // Evolve lattice and take a snapshot after every interval, the time is derived from the
+    // number of sweeps so that it does not accumulate rounding errors
+    let mut chain = Chain::default();
+    let time_step = parameters.time_step();
+    let steps = usize::max((parameters.snapshot_interval / time_step).round() as usize, 1);
+    let (mut observables, mut snapshots) = (Vec::new(), vec![(0.0, lattice.serialize())]);
+    let mut sweeps = 0;
+    for _ in 0..(parameters.dynamics_time / parameters.snapshot_interval).round() as usize {
+        let time = sweeps as f64 * time_step;
+        println!("[{}] D{} L{}: Dynamics at time={:.4}", host(), L::DIM, size, time);
+        let series = lattice.simulate(parameters, &mut chain, &mut rng, steps);
+        for (e, m) in series.energies.into_iter().zip(series.magnets) {
+            sweeps += 1;
+            observables.push(Observation::new(sweeps as f64 * time_step, e, m));
+        }
+        snapshots.push((sweeps as f64 * time_step, lattice.serialize()));
+    }
+
+    (observables, snapshots)
+}
+
+/// Runs the given number of independent quenches in parallel for every rate. Every quench starts
+/// from a lattice thermalized at the first temperature of the schedule. Returns the vortex density
+/// averaged over all quenches with its stddev at the recorded times of every rate.
+fn simulate_quench<L>(
+    parameters: Parameters,
+    size: usize,
+    schedule: Schedule,
+    knots: &[f64],
+    rates: &[f64],
+    quenches: usize,
+) -> Vec<Quench>
+where
+    L: Lattice,
+{
+    let mut results = Vec::new();
+    for &rate in rates {
+        println!("[{}] D{} L{}: Quenching with rate={:e}", host(), L::DIM, size, rate);
+        let sweeps = usize::max((rate * parameters.time_step()).recip().ceil() as usize, 1);
+        let runs = (0..quenches)
+            .into_par_iter()
+            .map_init(fastrand::Rng::new, |rng, _| {
+                // Quench lattice thermalized from a random configuration
+                let mut lattice = L::new(size, size, Boundaries::default(), knots[0].recip());
+                for i in 0..lattice.sites() {
+                    lattice.update_angle(i, rng.f64() * constants::MAX_ANGLE);
+                }
+                algorithm::quench::quench(
+                    rng,
+                    parameters,
+                    &mut lattice,
+                    |s| schedule.temperature(knots, s),
+                    sweeps,
+                    QUENCH_RECORDS,
+                    |lattice, time, temperature| {
+                        (time, temperature, lattice.vortex_density().unwrap_or_default())
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Average vortex density over all quenches at every recorded time
+        for k in 0..runs.first().map_or(0, Vec::len) {
+            let (time, temperature, _) = runs[0][k];
+            let densities = runs.iter().map(|r| r[k].2).collect::<Vec<_>>();
+            let mean = utils::mean(&densities);
+            let density = (mean, utils::stddev(&densities, mean).sqrt());
+            results.push(Quench::new(rate, time, temperature, density));
+        }
     }
 
     results
 }
 
+/// Searches the ground state of the lattice by simulated annealing from a random configuration
+/// along the schedule. Compares the lowest energy found with the ground state energy -DIM per
+/// spin of the clean model. Returns the annealing result.
+fn simulate_annealing<L>(
+    parameters: Parameters,
+    size: usize,
+    schedule: Schedule,
+    knots: &[f64],
+    sweeps: usize,
+) -> Annealing
+where
+    L: Lattice,
+{
+    // Initialize random number generator and lattice at infinite temperature
+    let mut rng = fastrand::Rng::new();
+    let mut lattice = L::new(size, size, Boundaries::default(), knots[0].recip());
+    for i in 0..lattice.sites() {
+        lattice.update_angle(i, rng.f64() * constants::MAX_ANGLE);
+    }
+
+    // Perform annealing and write console information
+    println!("[{}] D{} L{}: Annealing lattice", host(), L::DIM, size);
+    let result =
+        algorithm::annealing::anneal(&mut rng, parameters, &mut lattice, schedule, knots, sweeps);
+    println!(
+        "[{}] D{} L{}: e_min={:.6} deviation={:.2e} vortices={}",
+        host(),
+        L::DIM,
+        size,
+        result.energy,
+        result.energy + L::DIM as f64,
+        result.vortices
+    );
+
+    result
+}
+
+/// Measures the throughput of the scalar Metropolis sweep and the vectorized sweep close to the
+/// critical temperature of the 2D lattice. Both start from the same random configuration, propose
+/// uniformly on the whole circle and are thermalized before being timed. Only the bare sweeps are
+/// timed without any measurement. Prints the site updates per second of both paths.
+fn benchmark<L>(size: usize)
+where
+    L: Lattice,
+{
+    // Initialize random number generator and a shared random configuration
+    let mut rng = fastrand::Rng::new();
+    let angles = (0..size.pow(L::DIM as u32))
+        .map(|_| rng.f64() * constants::MAX_ANGLE)
+        .collect::<Vec<_>>();
+
+    let mut rates = Vec::new();
+    for (vectorized, name) in [(false, "scalar"), (true, "vectorized")] {
+        let beta = BENCHMARK_TEMPERATURE.recip();
+        let mut lattice = L::new(size, size, Boundaries::default(), beta);
+        for (i, &angle) in angles.iter().enumerate() {
+            lattice.update_angle(i, angle);
+        }
+        let (colors, mut simd) = (coloring(&lattice), SimdRng::new(&mut rng));
+        let mut sweep = |lattice: &mut L| {
+            if vectorized {
+                lattice.vectorized_sweep(&mut simd, &colors).0
+            } else {
+                lattice.sweep(&mut rng, constants::MAX_ANGLE / 2.0).0
+            }
+        };
+        for _ in 0..BENCHMARK_SWEEPS / 10 {
+            sweep(&mut lattice);
+        }
+
+        // Time the sweeps and write console information
+        let start = std::time::Instant::now();
+        let accepted = (0..BENCHMARK_SWEEPS).map(|_| sweep(&mut lattice)).sum::<usize>();
+        let elapsed = start.elapsed().as_secs_f64();
+        let rate = (BENCHMARK_SWEEPS * lattice.sites()) as f64 / elapsed;
+        let acceptance = accepted as f64 / (BENCHMARK_SWEEPS * lattice.sites()) as f64;
+        let energy = lattice.normalize_per_spin(lattice.energy());
+        println!(
+            "[{}] D{} L{}: {} updates/s={:.3e} acceptance={:.4} e={:.6}",
+            host(),
+            L::DIM,
+            size,
+            name,
+            rate,
+            acceptance,
+            energy
+        );
+        rates.push(rate);
+    }
+    println!("[{}] D{} L{}: speedup={:.2}", host(), L::DIM, size, rates[1] / rates[0]);
+}
+
+/// Simulates the lattice in double precision L and in single precision M at each of the
+/// validation temperatures. Prints the observables of both precisions and the deviation of the
+/// energy and magnetization in units of their combined error. Also prints the drift between the
+/// accumulated energy and the recalculated energy of the single precision lattice.
+fn validate<L, M>(parameters: Parameters, size: usize)
+where
+    L: Lattice,
+    M: Lattice,
+{
+    // Simulate both precisions at every temperature in parallel
+    println!("[{}] D{} L{}: Validating single precision", host(), L::DIM, size);
+    let results = VALIDATION_TEMPERATURES
+        .par_iter()
+        .map_init(fastrand::Rng::new, |rng, &t| {
+            let mut double = L::new(size, size, Boundaries::default(), t.recip());
+            let series = double.simulate(parameters, &mut Chain::default(), rng, VALIDATION_SWEEPS);
+            let e = analysis::complete(rng, series.energies, REWEIGHT_RESAMPLES);
+            let m = analysis::complete(rng, series.magnets, REWEIGHT_RESAMPLES);
+
+            let mut single = M::new(size, size, Boundaries::default(), t.recip());
+            let series = single.simulate(parameters, &mut Chain::default(), rng, VALIDATION_SWEEPS);
+            let drift = series.energies[VALIDATION_SWEEPS - 1]
+                - single.normalize_per_spin(single.energy());
+            let e_single = analysis::complete(rng, series.energies, REWEIGHT_RESAMPLES);
+            let m_single = analysis::complete(rng, series.magnets, REWEIGHT_RESAMPLES);
+
+            let cv = (
+                double.specific_heat_per_spin(&e).0,
+                single.specific_heat_per_spin(&e_single).0,
+            );
+            let xs = (
+                double.magnetic_susceptibility_per_spin(&m).0,
+                single.magnetic_susceptibility_per_spin(&m_single).0,
+            );
+            (t, (e, e_single), (m, m_single), cv, xs, drift)
+        })
+        .collect::<Vec<_>>();
+
+    // Write console information, the stddev of the observables holds the bootstrap variance
+    for (t, e, m, cv, xs, drift) in results {
+        println!(
+            "[{}] D{} L{}: t={:.4} e={:.6}/{:.6} ({:.2}σ) m={:.6}/{:.6} ({:.2}σ) cv={:.4}/{:.4} xs={:.4}/{:.4} drift={:.2e}",
+            host(),
+            L::DIM,
+            size,
+            t,
+            e.0.mean,
+            e.1.mean,
+            (e.0.mean - e.1.mean).abs() / (e.0.stddev + e.1.stddev).sqrt(),
+            m.0.mean,
+            m.1.mean,
+            (m.0.mean - m.1.mean).abs() / (m.0.stddev + m.1.stddev).sqrt(),
+            cv.0,
+            cv.1,
+            xs.0,
+            xs.1,
+            drift
+        );
+    }
+}
+
+fn simulate_density<L>(size: usize, temperatures: &[f64]) -> DensityOfStates
+where
+    L: Lattice,
+{
+    // Initialize random number generator and lattice in its ground state
+    let mut rng = fastrand::Rng::new();
+    let mut lattice = L::new(size, size, Boundaries::default(), 1.0);
+
+    // Estimate density of states
+    println!("[{}] D{} L{}: Estimating density of states", host(), L::DIM, size);
+    let density = lattice.wang_landau(&mut rng, BINS, FLATNESS, MIN_FACTOR);
+
+    // Evaluate thermodynamics at the requested temperatures
+    for &t in temperatures {
+        println!(
+            "[{}] D{} L{}: t={:.4} e={:.6} cv={:.6} f={:.6}",
+            host(),
+            L::DIM,
+            size,
+            t,
+            density.energy(t),
+            density.specific_heat(t),
+            density.free_energy(t)
+        );
+    }
+
+    density
+}
+
+/// Simulates the XY model with multicanonical weights from the given density of states and
+/// reweights the observables to the canonical ensemble at each of the given temperatures.
+fn simulate_multicanonical<L>(
+    size: usize,
+    density: &DensityOfStates,
+    temperatures: &[f64],
+) -> Vec<Configuration>
+where
+    L: Lattice,
+{
+    // Initialize random number generator and lattice at infinite temperature, since the
+    // walker would get stuck in the sparsely sampled bins close to the ground state. Its energy
+    // may lie above the range of the density, from which the walker descends into the range.
+    let mut rng = fastrand::Rng::new();
+    let mut lattice = L::new(size, size, Boundaries::default(), 1.0);
+    for i in 0..lattice.sites() {
+        lattice.update_angle(i, rng.f64() * constants::MAX_ANGLE);
+    }
+
+    // Perform multicanonical simulation and measure time
+    println!("[{}] D{} L{}: Multicanonical simulation", host(), L::DIM, size);
+    let start = std::time::Instant::now();
+    let series = lattice.multicanonical(&mut rng, density, SWEEPS);
+    let time_mc = start.elapsed().as_millis();
+
+    // Reweight to canonical ensembles in parallel
+    temperatures
+        .par_iter()
+        .map_init(fastrand::Rng::new, |rng, &t| {
+            let start = std::time::Instant::now();
+            let (e, m) = analysis::reweighting::multicanonical(
+                rng,
+                density,
+                &series.energies,
+                &series.magnets,
+                t,
+                REWEIGHT_RESAMPLES,
+            );
+
+            // Configurations derive Cv and Xs from the temperature of the lattice, the helicity
+            // modulus and the vortex density are left out as they are not reweighted
+            let lattice = L::new(size, size, Boundaries::default(), t.recip());
+            let time_boot = start.elapsed().as_millis();
+            Configuration::new(&lattice, e, m, None, series.acceptance, time_mc, time_boot)
+        })
+        .collect()
+}
+
 fn main() -> Result<(), rusqlite::Error> {
     // Parse CLI arguments and connect to SQLite database
-    let args = arguments::Arguments::parse();
+    let args = arguments::Arguments::parse().validate();
     let mut storage = storage::Storage::connect()?;
 
     // Some debug information for SBATCH
@@ -153,29 +843,155 @@ fn main() -> Result<(), rusqlite::Error> {
         Err(v) => println!("[{}] Could not fetch RAYON thread count: {}", host(), v),
     }
 
+    // Measure throughput without touching the database
+    if let Some(size) = args.benchmark {
+        match args.precision {
+            Precision::Double => benchmark::<Lattice2D>(size),
+            Precision::Single => benchmark::<Lattice2D<f32>>(size),
+            Precision::Discrete1024 => benchmark::<Lattice2D<Discrete<1024>>>(size),
+            Precision::Discrete65536 => benchmark::<Lattice2D<Discrete<65536>>>(size),
+        }
+        return Ok(());
+    }
+
+    // Compare the selected precision with double precision without touching the database
+    if let Some(size) = args.validate {
+        match args.precision {
+            Precision::Double => validate::<Lattice2D, Lattice2D>(args.parameters, size),
+            Precision::Single => validate::<Lattice2D, Lattice2D<f32>>(args.parameters, size),
+            Precision::Discrete1024 => {
+                validate::<Lattice2D, Lattice2D<Discrete<1024>>>(args.parameters, size)
+            }
+            Precision::Discrete65536 => {
+                validate::<Lattice2D, Lattice2D<Discrete<65536>>>(args.parameters, size)
+            }
+        }
+        return Ok(());
+    }
+
     // Fetches or creates the current run
     let run = match storage.get_run(args.run_id)? {
-        None => storage.create_run()?,
+        None => storage.create_run(args.states, args.geometry, args.precision)?,
         Some(run) => run,
     };
 
+    // Boundary conditions of the canonical simulations
+    let boundaries = Boundaries::new(&args.boundaries);
+
     // Ensure allocations are registered
-    storage.ensure_allocations(run.id, args.vortices, &args.one, &args.two)?;
+    storage.ensure_allocations(
+        run.id,
+        &[
+            (args.vortices, true),
+            (args.dynamics, false),
+            (args.quench, false),
+            (args.annealing, false),
+            (args.wang_landau, true),
+        ],
+        &args.one,
+        &args.two,
+        &args.three,
+        boundaries,
+    )?;
 
     // Simulate vortices
     if let Some(size) = args.vortices {
-        let results = simulate_vortices::<Lattice2D>(size);
-        storage.insert_vortices(run.id, Lattice2D::DIM, size, &results)?;
+        let results = simulate_vortices(args.parameters, size);
+        storage.insert_vortices(run.id, <Lattice2D>::DIM, size, &results)?;
+    }
+
+    // Simulate real time dynamics after a quench
+    if let Some(size) = args.dynamics {
+        let (observables, snapshots) = simulate_dynamics::<Lattice2D>(args.parameters, size);
+        storage.insert_dynamics(
+            run.id,
+            <Lattice2D>::DIM,
+            size,
+            args.parameters.dynamics_temperature,
+            &observables,
+            &snapshots,
+        )?;
+    }
+
+    // Simulate Kibble Zurek quenches
+    if let Some(size) = args.quench {
+        let results = simulate_quench::<Lattice2D>(
+            args.parameters,
+            size,
+            args.schedule,
+            &args.knots,
+            &args.rates,
+            args.quenches,
+        );
+        storage.insert_quenches(run.id, <Lattice2D>::DIM, size, &results)?;
+    }
+
+    // Search ground state by simulated annealing
+    if let Some(size) = args.annealing {
+        let result = simulate_annealing::<Lattice2D>(
+            args.parameters,
+            size,
+            args.schedule,
+            &args.knots,
+            args.annealing_sweeps,
+        );
+        storage.insert_annealing(run.id, <Lattice2D>::DIM, size, &result)?;
+    }
+
+    // Temperatures for the evaluation of the density of states
+    let temperatures = if args.temperatures.is_empty() {
+        range(0.0..3.0, STEPS).0.collect()
+    } else {
+        args.temperatures.clone()
+    };
+
+    // Estimate density of states
+    if let Some(size) = args.wang_landau {
+        let density = simulate_density::<Lattice2D>(size, &temperatures);
+        storage.insert_density(run.id, <Lattice2D>::DIM, size, &density)?;
+
+        // Replace canonical simulations of this size by a single multicanonical simulation
+        if args.multicanonical && storage.claim_allocation(run.id, <Lattice2D>::DIM, size)? {
+            let configurations =
+                simulate_multicanonical::<Lattice2D>(size, &density, &temperatures);
+            storage.insert_results(
+                run.id,
+                <Lattice2D>::DIM,
+                size,
+                size,
+                Boundaries::default(),
+                &configurations,
+            )?;
+        }
     }
 
     // While a next allocation is available => process it
-    while let Some((dimension, size)) = storage.next_allocation(run.id)? {
-        println!("[{}] Next allocation: D{} L{}", host(), dimension, size);
-        let configurations = match dimension {
-            1 => simulate::<Lattice1D>(size),
-            _ => simulate::<Lattice2D>(size),
+    while let Some((dimension, size, height)) = storage.next_allocation(run.id, boundaries)? {
+        println!(
+            "[{}] Next allocation: D{} L{} H{}",
+            host(),
+            dimension,
+            size,
+            height
+        );
+        let (configurations, exchanges, reweighted) = match args.states {
+            Some(states) => simulate_clock(args.parameters, states, size, height, boundaries),
+            None => simulate_precision(
+                args.parameters,
+                dimension,
+                args.geometry,
+                args.precision,
+                size,
+                height,
+                boundaries,
+            ),
         };
-        storage.insert_results(run.id, dimension, size, &configurations)?;
+        storage.insert_exchanges(run.id, dimension, size, height, boundaries, &exchanges)?;
+        if let Some((single, multi)) = reweighted {
+            let curves = [("single", &single), ("multi", &multi)];
+            storage.insert_reweighted(run.id, dimension, size, height, boundaries, &curves)?;
+        }
+        storage.insert_results(run.id, dimension, size, height, boundaries, &configurations)?;
     }
     Ok(())
 }
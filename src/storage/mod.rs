@@ -1,51 +1,94 @@
-use rusqlite::{params, Connection, OptionalExtension};
+use clap::ValueEnum;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
 use std::str::FromStr;
 
 mod types;
 
+use crate::algorithm::annealing::Annealing;
+use crate::analysis::reweighting::Reweighted;
+use crate::analysis::DensityOfStates;
+use crate::lattice::{Boundaries, Geometry, Precision};
 use crate::utils;
 pub use types::*;
 
-/// Includes the migration SQL script
-const MIGRATION: &str = include_str!("../../migrations/20250103215725_schema.sql");
+/// Includes the migration SQL scripts, where the schema version of a database is the number of
+/// scripts which were run on it
+const MIGRATIONS: [&str; 3] = [
+    include_str!("../../migrations/20250103215725_schema.sql"),
+    include_str!("../../migrations/20261018120000_upgrade.sql"),
+    include_str!("../../migrations/20261018140000_canonical.sql"),
+];
 
 /// The storage struct manages the SQLite connection and data insertion.
 pub struct Storage(Connection);
 
 impl Storage {
     /// Connects to the 'output.sqlite' database in the current working directory,
-    /// runs the migration script and sets some PRAGMA settings.
+    /// runs the outstanding migration scripts and sets some PRAGMA settings.
     pub fn connect() -> Result<Self, rusqlite::Error> {
-        let conn = Connection::open("output.sqlite")?;
+        let mut conn = Connection::open("output.sqlite")?;
         conn.pragma_update(None, "synchronous", "NORMAL")?;
-        conn.execute_batch(MIGRATION)?;
+        Self::migrate(&mut conn)?;
         Ok(Self(conn))
     }
 
+    /// Runs the migration scripts past the schema version of the database, which is stored as
+    /// its user version. Databases created before the versioning have version zero, since the
+    /// first script only creates missing tables. The scripts run in a single immediate
+    /// transaction so that concurrent processes migrate the database once. Foreign keys are
+    /// disabled meanwhile, since the scripts rebuild referenced tables.
+    fn migrate(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+        conn.pragma_update(None, "foreign_keys", false)?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let version: usize = tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        for migration in MIGRATIONS.iter().skip(version) {
+            tx.execute_batch(migration)?;
+        }
+        tx.pragma_update(None, "user_version", version.max(MIGRATIONS.len()))?;
+        tx.commit()?;
+        conn.pragma_update(None, "foreign_keys", true)
+    }
+
+    /// Registers the allocations of the run. The lattices are registered with the given boundary
+    /// conditions, the square 2D lattices of the single sizes always with periodic ones, which the
+    /// tables of the single sizes store as the default of their boundaries column. Every single
+    /// size comes with whether it is simulated canonically as well. Lattices registered several
+    /// times are simulated canonically if any of the registrations asks for it.
     pub fn ensure_allocations(
         &mut self,
         id: i32,
-        vortices: Option<usize>,
+        singles: &[(Option<usize>, bool)],
         one: &[usize],
-        two: &[usize],
+        two: &[(usize, usize)],
+        three: &[(usize, usize)],
+        boundaries: Boundaries,
     ) -> Result<(), rusqlite::Error> {
         // Prepares the transaction and statement
+        let (periodic, boundaries) = (Boundaries::default().to_string(), boundaries.to_string());
         let tx = self.0.transaction()?;
-        let mut stmt = tx.prepare("INSERT INTO allocations (run_id, dimension, size) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")?;
+        let mut stmt = tx.prepare("INSERT INTO allocations (run_id, dimension, size, height, boundaries, canonical) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (run_id, dimension, size, height, boundaries) DO UPDATE SET canonical = MAX(canonical, excluded.canonical)")?;
 
-        // Ensure 2D lattice size is registered for vortex development
-        if let Some(size) = vortices {
-            stmt.execute(params![id, 2, size])?;
+        // Ensure square 2D lattice sizes are registered for vortex development, real time
+        // dynamics, quenches, annealing and the density of states
+        for (size, canonical) in singles {
+            if let Some(size) = size {
+                stmt.execute(params![id, 2, size, size, periodic, canonical])?;
+            }
         }
 
         // Ensure all 1D lattice sizes are registered
         for val in one {
-            stmt.execute(params![id, 1, val])?;
+            stmt.execute(params![id, 1, val, 1, boundaries, true])?;
         }
 
         // Ensure all 2D lattice sizes are registered
-        for val in two {
-            stmt.execute(params![id, 2, val])?;
+        for (size, height) in two {
+            stmt.execute(params![id, 2, size, height, boundaries, true])?;
+        }
+
+        // Ensure all 3D lattice sizes are registered
+        for (size, height) in three {
+            stmt.execute(params![id, 3, size, height, boundaries, true])?;
         }
 
         // Commit the transaction
@@ -53,9 +96,14 @@ impl Storage {
         tx.commit()
     }
 
-    /// Queries for the next unassigned allocation and returns the corresponding lattice size and
-    /// dimensionality. Returns none if there are no outstanding allocations.
-    pub fn next_allocation(&mut self, id: i32) -> Result<Option<(usize, usize)>, rusqlite::Error> {
+    /// Queries for the next unassigned allocation of the canonical scans with the given boundary
+    /// conditions and returns the corresponding dimensionality, lattice size and height. Returns
+    /// none if there are no outstanding allocations.
+    pub fn next_allocation(
+        &mut self,
+        id: i32,
+        boundaries: Boundaries,
+    ) -> Result<Option<(usize, usize, usize)>, rusqlite::Error> {
         // Retrieve hostname and process id
         let node = std::env::var("SLURMD_NODENAME").unwrap_or_else(|_| utils::host());
         let process = match std::env::var("SLURM_PROCID").map(|x| u32::from_str(&x)) {
@@ -64,11 +112,11 @@ impl Storage {
         };
 
         // Build parameters and start transaction
-        let params = (node, process, utils::unix_time(), id);
+        let params = (node, process, utils::unix_time(), id, boundaries.to_string());
         let tx = self.0.transaction()?;
 
         // Prepare statement and execute
-        let mut stmt = tx.prepare("UPDATE allocations SET node = $1, process = $2, allocated_at = $3 WHERE id IN (SELECT id FROM allocations WHERE run_id = $4 AND node IS NULL ORDER BY size DESC LIMIT 1) RETURNING *")?;
+        let mut stmt = tx.prepare("UPDATE allocations SET node = $1, process = $2, allocated_at = $3 WHERE id IN (SELECT id FROM allocations WHERE run_id = $4 AND boundaries = $5 AND canonical AND node IS NULL ORDER BY size * height DESC LIMIT 1) RETURNING *")?;
         let result = stmt.query_row(params, Self::row_to_allocation).optional()?;
 
         // Commit transaction
@@ -77,6 +125,29 @@ impl Storage {
         Ok(result)
     }
 
+    /// Assigns the allocation of the periodic square lattice with the given size and
+    /// dimensionality to this process so that it is not simulated canonically. Returns false if
+    /// it was already assigned.
+    pub fn claim_allocation(
+        &mut self,
+        id: i32,
+        dimension: usize,
+        size: usize,
+    ) -> Result<bool, rusqlite::Error> {
+        // Retrieve hostname and process id
+        let node = std::env::var("SLURMD_NODENAME").unwrap_or_else(|_| utils::host());
+        let process = match std::env::var("SLURM_PROCID").map(|x| u32::from_str(&x)) {
+            Ok(Ok(id)) => id,
+            _ => std::process::id(),
+        };
+
+        // Prepare statement and execute
+        let periodic = Boundaries::default().to_string();
+        let params = params![node, process, utils::unix_time(), id, dimension, size, periodic];
+        let mut stmt = self.0.prepare("UPDATE allocations SET node = $1, process = $2, allocated_at = $3 WHERE run_id = $4 AND dimension = $5 AND size = $6 AND height = $6 AND boundaries = $7 AND node IS NULL")?;
+        Ok(stmt.execute(params)? > 0)
+    }
+
     /// Retrieves the run with the given id if said id is not None. Otherwise, or if the run
     /// does not exist, return None.
     pub fn get_run(&mut self, id: Option<i32>) -> Result<Option<Run>, rusqlite::Error> {
@@ -90,14 +161,27 @@ impl Storage {
         stmt.query_row(params, Self::row_to_run).optional()
     }
 
-    /// Creates a new run and returns it.
-    pub fn create_run(&mut self) -> Result<Run, rusqlite::Error> {
+    /// Creates a new run and returns it. The number of states is given for runs of the clock
+    /// model and none for runs of the XY model. The geometry applies to the 2D lattices and the
+    /// precision to the spins of the XY model.
+    pub fn create_run(
+        &mut self,
+        states: Option<usize>,
+        geometry: Geometry,
+        precision: Precision,
+    ) -> Result<Run, rusqlite::Error> {
         // Prepare transaction and parameters
         let tx = self.0.transaction()?;
-        let params = (utils::unix_time(),);
+        let geometry = geometry
+            .to_possible_value()
+            .map(|v| v.get_name().to_owned());
+        let precision = precision
+            .to_possible_value()
+            .map(|v| v.get_name().to_owned());
+        let params = (utils::unix_time(), states, geometry, precision);
 
         // Insert run and convert to run struct
-        let mut stmt = tx.prepare("INSERT INTO runs (created_at) VALUES ($1) RETURNING *")?;
+        let mut stmt = tx.prepare("INSERT INTO runs (created_at, states, geometry, precision) VALUES ($1, $2, $3, $4) RETURNING *")?;
         let result = stmt.query_row(params, Self::row_to_run)?;
 
         // Commit transaction
@@ -111,34 +195,47 @@ impl Storage {
         Ok(Run { id: row.get(0)? })
     }
 
-    /// Converts the SQL row into the lattice size and dimensionality.
-    fn row_to_allocation(row: &rusqlite::Row) -> rusqlite::Result<(usize, usize)> {
-        Ok((row.get(2)?, row.get(3)?))
+    /// Converts the SQL row into the dimensionality, lattice size and height.
+    fn row_to_allocation(row: &rusqlite::Row) -> rusqlite::Result<(usize, usize, usize)> {
+        Ok((row.get(2)?, row.get(3)?, row.get(4)?))
     }
 
     /// Inserts the result configurations into the SQLite database. Takes the run id, the lattice
-    /// size and dimensionality for which these configurations were generated.
+    /// size, height and dimensionality and the boundary conditions for which these
+    /// configurations were generated, which together identify the allocation. The twist angle of
+    /// every direction of the lattice is stored, free boundaries and missing directions are
+    /// stored as null.
     pub fn insert_results(
         &mut self,
         id: i32,
         dimension: usize,
         size: usize,
+        height: usize,
+        boundaries: Boundaries,
         configurations: &[Configuration],
     ) -> Result<(), rusqlite::Error> {
         // Prepare transaction and statment
         let tx = self.0.transaction()?;
         let mut stmt = tx.prepare("
-            INSERT INTO results (run_id, dimension, size, temperature, energy, energy_std, energy_tau, energy_sqr, energy_sqr_std, energy_sqr_tau, magnet, magnet_std, magnet_tau, magnet_sqr, magnet_sqr_std, magnet_sqr_tau, specific_heat, specific_heat_std, magnet_suscept, magnet_suscept_std, time_mc, time_boot)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22) ON CONFLICT DO NOTHING
+            INSERT INTO results (run_id, dimension, size, height, boundaries, temperature, twist_x, twist_y, twist_z, energy, energy_std, energy_tau, energy_sqr, energy_sqr_std, energy_sqr_tau, magnet, magnet_std, magnet_tau, magnet_sqr, magnet_sqr_std, magnet_sqr_tau, specific_heat, specific_heat_std, magnet_suscept, magnet_suscept_std, helicity, helicity_std, vortex_density, vortex_density_std, vortex_density_tau, acceptance, violation, violation_sqr, violation_exp, demon_temperature, time_mc, time_boot)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36, $37) ON CONFLICT DO NOTHING
         ")?;
 
         // Insert configurations
+        let label = boundaries.to_string();
+        let mut twists = boundaries.twists();
+        twists[dimension..].fill(None);
         for cfg in configurations {
             stmt.execute(rusqlite::params![
                 id,
                 cfg.dimension as i32,
                 size as i32,
+                height as i32,
+                label,
                 cfg.temperature,
+                twists[0],
+                twists[1],
+                twists[2],
                 cfg.energy.mean,
                 cfg.energy.stddev,
                 cfg.energy.tau,
@@ -155,6 +252,16 @@ impl Storage {
                 cfg.cv.1,
                 cfg.xs.0,
                 cfg.xs.1,
+                cfg.helicity.map(|h| h.0),
+                cfg.helicity.map(|h| h.1),
+                cfg.vortices.as_ref().map(|v| v.mean),
+                cfg.vortices.as_ref().map(|v| v.stddev),
+                cfg.vortices.as_ref().map(|v| v.tau),
+                cfg.acceptance,
+                cfg.violation.map(|v| v.mean),
+                cfg.violation.map(|v| v.sqr_mean),
+                cfg.violation.map(|v| v.exp_mean),
+                cfg.demon_temperature,
                 cfg.time_mc as i32,
                 cfg.time_boot as i32
             ])?;
@@ -162,9 +269,240 @@ impl Storage {
         drop(stmt);
 
         // Set allocation to finished
-        let mut stmt = tx.prepare("UPDATE allocations SET finished_at = $1 WHERE run_id = $2 AND dimension = $3 AND size = $4 AND allocated_at NOT NULL")?;
-        stmt.execute(params![utils::unix_time(), id, dimension, size])?;
+        let mut stmt = tx.prepare("UPDATE allocations SET finished_at = $1 WHERE run_id = $2 AND dimension = $3 AND size = $4 AND height = $5 AND boundaries = $6 AND allocated_at NOT NULL")?;
+        stmt.execute(params![utils::unix_time(), id, dimension, size, height, label])?;
+
+        drop(stmt);
+        tx.commit()
+    }
+
+    /// Inserts the swap acceptance rates of neighbouring temperatures from parallel tempering.
+    /// Takes the run id, the lattice size, height and dimensionality and the boundary conditions
+    /// for which they were measured.
+    pub fn insert_exchanges(
+        &mut self,
+        id: i32,
+        dimension: usize,
+        size: usize,
+        height: usize,
+        boundaries: Boundaries,
+        exchanges: &[Exchange],
+    ) -> Result<(), rusqlite::Error> {
+        // Prepare transaction and statement
+        let tx = self.0.transaction()?;
+        let mut stmt = tx.prepare("INSERT INTO exchanges (run_id, dimension, size, height, boundaries, temperature_low, temperature_high, acceptance) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT DO NOTHING")?;
+
+        // Insert exchanges
+        let label = boundaries.to_string();
+        for exchange in exchanges {
+            stmt.execute(params![
+                id,
+                dimension,
+                size,
+                height,
+                label,
+                exchange.low,
+                exchange.high,
+                exchange.acceptance
+            ])?;
+        }
+
+        // Commit transaction
+        drop(stmt);
+        tx.commit()
+    }
+
+    /// Inserts the log density of states of every visited energy bin. The energy is stored per
+    /// spin at the center of each bin. Takes the run id, the square lattice size and dimensionality.
+    pub fn insert_density(
+        &mut self,
+        id: i32,
+        dimension: usize,
+        size: usize,
+        density: &DensityOfStates,
+    ) -> Result<(), rusqlite::Error> {
+        // Prepare transaction and statement
+        let tx = self.0.transaction()?;
+        let mut stmt = tx.prepare("INSERT INTO densities (run_id, dimension, size, height, energy, ln_g) VALUES ($1, $2, $3, $3, $4, $5) ON CONFLICT DO NOTHING")?;
+
+        // Insert visited bins
+        for (bin, ln_g) in density.ln_g.iter().enumerate() {
+            if ln_g.is_finite() {
+                let energy = density.energy_of(bin) / density.sites as f64;
+                stmt.execute(params![id, dimension, size, energy, ln_g])?;
+            }
+        }
+
+        // Commit transaction
+        drop(stmt);
+        tx.commit()
+    }
+
+    /// Inserts the reweighted specific heat and susceptibility curves and their peaks. Takes the
+    /// run id, the lattice size, height and dimensionality, the boundary conditions and the
+    /// reweighted curves together with the name of their reweighting method.
+    pub fn insert_reweighted(
+        &mut self,
+        id: i32,
+        dimension: usize,
+        size: usize,
+        height: usize,
+        boundaries: Boundaries,
+        reweighted: &[(&str, &Reweighted)],
+    ) -> Result<(), rusqlite::Error> {
+        // Prepare transaction and statement
+        let label = boundaries.to_string();
+        let tx = self.0.transaction()?;
+        let mut stmt = tx.prepare("INSERT INTO reweighted (run_id, dimension, size, height, boundaries, method, temperature, specific_heat, specific_heat_std, magnet_suscept, magnet_suscept_std) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) ON CONFLICT DO NOTHING")?;
+
+        // Insert curves
+        for (method, reweighted) in reweighted {
+            for curve in &reweighted.curves {
+                stmt.execute(params![
+                    id,
+                    dimension,
+                    size,
+                    height,
+                    label,
+                    method,
+                    curve.temperature,
+                    curve.cv.0,
+                    curve.cv.1,
+                    curve.xs.0,
+                    curve.xs.1
+                ])?;
+            }
+        }
+        drop(stmt);
+
+        // Insert peaks
+        let mut stmt = tx.prepare("INSERT INTO peaks (run_id, dimension, size, height, boundaries, method, observable, temperature, temperature_std, value, value_std) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) ON CONFLICT DO NOTHING")?;
+        for (method, reweighted) in reweighted {
+            let peaks = [
+                ("specific_heat", &reweighted.cv_peak),
+                ("magnet_suscept", &reweighted.xs_peak),
+            ];
+            for (observable, peak) in peaks {
+                stmt.execute(params![
+                    id,
+                    dimension,
+                    size,
+                    height,
+                    label,
+                    method,
+                    observable,
+                    peak.temperature.0,
+                    peak.temperature.1,
+                    peak.value.0,
+                    peak.value.1
+                ])?;
+            }
+        }
+
+        // Commit transaction
+        drop(stmt);
+        tx.commit()
+    }
+
+    /// Inserts the time resolved energy and magnetization per spin and the snapshots of the
+    /// real time dynamics. Takes the run id, the square lattice size and dimensionality and the
+    /// temperature the lattice was quenched to.
+    pub fn insert_dynamics(
+        &mut self,
+        id: i32,
+        dimension: usize,
+        size: usize,
+        temperature: f64,
+        observables: &[Observation],
+        snapshots: &[(f64, String)],
+    ) -> Result<(), rusqlite::Error> {
+        // Prepare transaction and statement
+        let tx = self.0.transaction()?;
+        let mut stmt = tx.prepare("INSERT INTO dynamics (run_id, dimension, size, height, temperature, time, energy, magnet) VALUES ($1, $2, $3, $3, $4, $5, $6, $7) ON CONFLICT DO NOTHING")?;
+
+        // Insert observables
+        for obs in observables {
+            stmt.execute(params![
+                id,
+                dimension,
+                size,
+                temperature,
+                obs.time,
+                obs.energy,
+                obs.magnet
+            ])?;
+        }
+        drop(stmt);
+
+        // Insert snapshots
+        let mut stmt = tx.prepare("INSERT INTO snapshots (run_id, dimension, size, height, temperature, time, spins) VALUES ($1, $2, $3, $3, $4, $5, $6) ON CONFLICT DO NOTHING")?;
+        for (time, spins) in snapshots {
+            stmt.execute(params![id, dimension, size, temperature, time, spins])?;
+        }
 
+        // Commit transaction
+        drop(stmt);
+        tx.commit()
+    }
+
+    /// Inserts the mean vortex density of the quenches at every recorded time and rate. Takes
+    /// the run id, the square lattice size and dimensionality.
+    pub fn insert_quenches(
+        &mut self,
+        id: i32,
+        dimension: usize,
+        size: usize,
+        quenches: &[Quench],
+    ) -> Result<(), rusqlite::Error> {
+        // Prepare transaction and statement
+        let tx = self.0.transaction()?;
+        let mut stmt = tx.prepare("INSERT INTO quenches (run_id, dimension, size, height, rate, time, temperature, density, density_std) VALUES ($1, $2, $3, $3, $4, $5, $6, $7, $8) ON CONFLICT DO NOTHING")?;
+
+        // Insert quenches
+        for quench in quenches {
+            stmt.execute(params![
+                id,
+                dimension,
+                size,
+                quench.rate,
+                quench.time,
+                quench.temperature,
+                quench.density.0,
+                quench.density.1
+            ])?;
+        }
+
+        // Commit transaction
+        drop(stmt);
+        tx.commit()
+    }
+
+    /// Inserts the lowest energy configuration found by simulated annealing together with its
+    /// energy per spin, the residual vortex count and the energy trajectory. Takes the run id,
+    /// the square lattice size and dimensionality.
+    pub fn insert_annealing(
+        &mut self,
+        id: i32,
+        dimension: usize,
+        size: usize,
+        annealing: &Annealing,
+    ) -> Result<(), rusqlite::Error> {
+        // Prepare transaction and statement
+        let tx = self.0.transaction()?;
+        let mut stmt = tx.prepare("INSERT INTO annealings (run_id, dimension, size, height, energy, vortices, spins, trajectory) VALUES ($1, $2, $3, $3, $4, $5, $6, $7)")?;
+
+        // Insert annealing
+        stmt.execute(params![
+            id,
+            dimension,
+            size,
+            annealing.energy,
+            annealing.vortices,
+            serde_json::to_string(&annealing.spins).unwrap(),
+            serde_json::to_string(&annealing.trajectory).unwrap()
+        ])?;
+
+        // Commit transaction
         drop(stmt);
         tx.commit()
     }
@@ -174,15 +512,23 @@ impl Storage {
         id: i32,
         dimension: usize,
         size: usize,
-        vortices: &[(f64, String)]
+        snapshots: &[Snapshot],
     ) -> Result<(), rusqlite::Error> {
         // Prepare transaction and statement
         let tx = self.0.transaction()?;
-        let mut stmt = tx.prepare("INSERT INTO vortices (run_id, dimension, size, temperature, spins) VALUES ($1, $2, $3, $4, $5)")?;
+        let mut stmt = tx.prepare("INSERT INTO vortices (run_id, dimension, size, height, temperature, spins, vortices, antivortices) VALUES ($1, $2, $3, $3, $4, $5, $6, $7)")?;
 
         // Insert vortices
-        for (temperature, spins) in vortices {
-            stmt.execute(params![id, dimension, size, temperature, spins])?;
+        for snapshot in snapshots {
+            stmt.execute(params![
+                id,
+                dimension,
+                size,
+                snapshot.temperature,
+                snapshot.spins,
+                serde_json::to_string(&snapshot.vortices).unwrap(),
+                serde_json::to_string(&snapshot.antivortices).unwrap()
+            ])?;
         }
 
         // Commit transaction
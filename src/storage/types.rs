@@ -1,3 +1,4 @@
+use crate::algorithm::hybrid::Violation;
 use crate::analysis::Observable;
 use crate::lattice::Lattice;
 use std::cmp::Ordering;
@@ -6,6 +7,79 @@ pub struct Run {
     pub id: i32,
 }
 
+pub struct Exchange {
+    pub low: f64,
+    pub high: f64,
+    pub acceptance: f64,
+}
+
+impl Exchange {
+    pub const fn new(low: f64, high: f64, acceptance: f64) -> Self {
+        Self {
+            low,
+            high,
+            acceptance,
+        }
+    }
+}
+
+pub struct Observation {
+    pub time: f64,
+    pub energy: f64,
+    pub magnet: f64,
+}
+
+impl Observation {
+    pub const fn new(time: f64, energy: f64, magnet: f64) -> Self {
+        Self {
+            time,
+            energy,
+            magnet,
+        }
+    }
+}
+
+pub struct Snapshot {
+    pub temperature: f64,
+    pub spins: String,
+    pub vortices: Vec<usize>,
+    pub antivortices: Vec<usize>,
+}
+
+impl Snapshot {
+    pub const fn new(
+        temperature: f64,
+        spins: String,
+        vortices: Vec<usize>,
+        antivortices: Vec<usize>,
+    ) -> Self {
+        Self {
+            temperature,
+            spins,
+            vortices,
+            antivortices,
+        }
+    }
+}
+
+pub struct Quench {
+    pub rate: f64,
+    pub time: f64,
+    pub temperature: f64,
+    pub density: (f64, f64),
+}
+
+impl Quench {
+    pub const fn new(rate: f64, time: f64, temperature: f64, density: (f64, f64)) -> Self {
+        Self {
+            rate,
+            time,
+            temperature,
+            density,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Configuration {
     pub dimension: usize,
@@ -14,15 +88,26 @@ pub struct Configuration {
     pub magnetization: Observable,
     pub cv: (f64, f64),
     pub xs: (f64, f64),
+    pub helicity: Option<(f64, f64)>,
+    pub vortices: Option<Observable>,
+    pub acceptance: f64,
+    pub violation: Option<Violation>,
+    pub demon_temperature: Option<f64>,
     pub time_mc: u128,
     pub time_boot: u128,
 }
 
 impl Configuration {
+    /// Creates the configuration and derives Cv, Xs and the helicity modulus from the temperature
+    /// of the lattice. The helicity modulus requires the coupling and the current observables,
+    /// the vortex density, the violation statistics and the demon temperature are left out and
+    /// may be set afterwards.
     pub fn new<L>(
         lattice: &L,
         energy: Observable,
         magnetization: Observable,
+        helicity: Option<(Observable, Observable)>,
+        acceptance: f64,
         time_mc: u128,
         time_boot: u128,
     ) -> Self
@@ -34,17 +119,19 @@ impl Configuration {
             temperature: lattice.temperature(),
             cv: lattice.specific_heat_per_spin(&energy),
             xs: lattice.magnetic_susceptibility_per_spin(&magnetization),
+            helicity: helicity
+                .map(|(coupling, current)| lattice.helicity_modulus(&coupling, &current)),
+            vortices: None,
             energy,
             magnetization,
+            acceptance,
+            violation: None,
+            demon_temperature: None,
             time_mc,
             time_boot,
         }
     }
 
-    pub fn relevant_tmp(x: &&Configuration) -> bool {
-        x.temperature >= 0.80 && x.temperature <= 1.2
-    }
-
     pub fn cmp(a: &&Configuration, b: &&Configuration) -> Ordering {
         a.xs.0.total_cmp(&b.xs.0)
     }